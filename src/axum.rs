@@ -0,0 +1,108 @@
+//! Query-string types for axum handlers, used as `Query<Pagination>` and
+//! `Query<Sorting>`.
+
+use serde::Deserialize;
+
+use crate::{BuildError, Order, QueryBuilder};
+
+/// `?page=2&per_page=50`. Both parameters are optional.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct Pagination {
+    /// 1-based page number; defaults to the first page.
+    pub page: Option<u64>,
+    /// Page size; defaults to [`Pagination::DEFAULT_PER_PAGE`].
+    pub per_page: Option<u64>,
+}
+
+impl Pagination {
+    /// Page size used when the request does not specify one.
+    pub const DEFAULT_PER_PAGE: u64 = 20;
+
+    /// Returns `(page, per_page)` with the page at least 1 and the page size
+    /// clamped to `1..=max_per_page`.
+    pub fn clamped(&self, max_per_page: u64) -> (u64, u64) {
+        let page = self.page.unwrap_or(1).max(1);
+        let per_page = self
+            .per_page
+            .unwrap_or(Self::DEFAULT_PER_PAGE)
+            .clamp(1, max_per_page.max(1));
+        (page, per_page)
+    }
+}
+
+/// `?sort=created_at&order=desc`. The order defaults to ascending.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Sorting {
+    /// The field to sort by.
+    pub sort: Option<String>,
+    /// `asc` or `desc`.
+    pub order: Option<Order>,
+}
+
+impl QueryBuilder {
+    /// Applies request pagination, clamping the page size to `max_per_page`.
+    pub fn paginate_request(&mut self, pagination: &Pagination, max_per_page: u64) -> &mut Self {
+        let (page, per_page) = pagination.clamped(max_per_page);
+        self.paginate(page, per_page)
+    }
+
+    /// Applies request sorting. The field must be one of `allowed`, since it
+    /// comes straight from the client; no sort field is a no-op.
+    pub fn order_by_spec(
+        &mut self,
+        sorting: &Sorting,
+        allowed: &[&str],
+    ) -> Result<&mut Self, BuildError> {
+        let Some(field) = sorting.sort.as_deref() else {
+            return Ok(self);
+        };
+        if !allowed.contains(&field) {
+            return Err(BuildError::new("Sort field is not allowed.")
+                .with_suggestion("add the field to the allowed list"));
+        }
+        Ok(self.order_by_field(field, sorting.order.unwrap_or(Order::Asc)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::axum::extract::Query;
+    use ::axum::http::Uri;
+
+    fn query<T: serde::de::DeserializeOwned>(uri: &str) -> T {
+        Query::<T>::try_from_uri(&uri.parse::<Uri>().unwrap())
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn pagination_and_sorting_from_query_string() {
+        let pagination: Pagination = query("/posts?page=0&per_page=500");
+        let sorting: Sorting = query("/posts?sort=created_at&order=desc");
+        let sql = QueryBuilder::new()
+            .from("post")
+            .order_by_spec(&sorting, &["created_at", "title"])
+            .unwrap()
+            .paginate_request(&pagination, 100)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM post ORDER BY created_at DESC LIMIT 100 START 0"
+        );
+
+        let defaults: Pagination = query("/posts");
+        assert_eq!(defaults.clamped(100), (1, 20));
+    }
+
+    #[test]
+    fn unknown_sort_field_is_rejected() {
+        let sorting: Sorting = query("/posts?sort=password");
+        let err = QueryBuilder::new()
+            .from("user")
+            .order_by_spec(&sorting, &["name"])
+            .unwrap_err();
+        assert_eq!(err, "Sort field is not allowed.");
+    }
+}
@@ -0,0 +1,174 @@
+//! Multi-statement requests without a transaction wrapper, with the
+//! response index of every statement.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    BuildError, QueryBuilder, ScriptBuilder, StatementKind, Value, strip_comments, terminate,
+};
+
+/// Collects independent statements into one request. SurrealDB answers a
+/// multi-statement request with one result per statement, so
+/// [`BatchBuilder::build_indexed`] reports which result belongs to which
+/// statement.
+#[derive(Debug, Default)]
+pub struct BatchBuilder {
+    statements: Vec<String>,
+    /// Labels keyed by the index of the statement they describe.
+    labels: BTreeMap<usize, String>,
+    bindings: BTreeMap<String, Value>,
+}
+
+/// A batch script together with the response index of each statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedBatch {
+    /// The script, identical to `BatchBuilder::build()`.
+    pub script: String,
+    /// One entry per statement, in execution order. Comment lines are
+    /// omitted since they produce no result.
+    pub statements: Vec<BatchEntry>,
+}
+
+/// A statement of a built batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry {
+    /// The label given with `BatchBuilder::label`, if any.
+    pub label: Option<String>,
+    /// The statement kind.
+    pub kind: StatementKind,
+    /// Index of the statement's result in the response array.
+    pub index: usize,
+}
+
+impl IndexedBatch {
+    /// The response index of the statement labelled `label`.
+    pub fn index_of(&self, label: &str) -> Option<usize> {
+        self.statements
+            .iter()
+            .find(|s| s.label.as_deref() == Some(label))
+            .map(|s| s.index)
+    }
+}
+
+impl BatchBuilder {
+    /// Create a new empty batch.
+    pub fn new() -> Self {
+        Self {
+            statements: Vec::new(),
+            labels: BTreeMap::new(),
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Binds a value to the `$name` parameter; a leading `$` is optional.
+    pub fn bind(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        self.bindings
+            .insert(name.trim_start_matches('$').to_string(), value.into());
+        self
+    }
+
+    /// The bound parameters.
+    pub fn bindings(&self) -> &BTreeMap<String, Value> {
+        &self.bindings
+    }
+
+    /// Labels the most recently added statement, so its response index can
+    /// be looked up with [`IndexedBatch::index_of`].
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        if let Some(last) = self.statements.len().checked_sub(1) {
+            self.labels.insert(last, label.to_string());
+        }
+        self
+    }
+
+    /// Add a single raw statement (terminated with a semicolon if missing).
+    pub fn add_statement(&mut self, stmt: &str) -> &mut Self {
+        self.statements.push(terminate(stmt));
+        self
+    }
+
+    /// Add a `QueryBuilder`'s built query as a statement.
+    pub fn add_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.add_statement(&q))
+    }
+
+    /// Add every statement of a `ScriptBuilder`, each taking its own
+    /// response index; a label then applies to the last one (usually the
+    /// RETURN). The script's bindings are merged into the batch's.
+    pub fn add_script(&mut self, script: &ScriptBuilder) -> Result<&mut Self, BuildError> {
+        let statements = script.render_statements().collect::<Result<Vec<_>, _>>()?;
+        self.statements
+            .extend(statements.iter().map(|s| s.trim().to_string()));
+        for (name, value) in script.bindings() {
+            self.bindings
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        Ok(self)
+    }
+
+    /// Build the request, one statement per line.
+    pub fn build(&self) -> String {
+        self.statements.join("\n")
+    }
+
+    /// Build the request along with the response index of every statement.
+    pub fn build_indexed(&self) -> IndexedBatch {
+        let mut statements = Vec::new();
+        for (i, st) in self.statements.iter().enumerate() {
+            let code = strip_comments(st);
+            if code.is_empty() {
+                continue;
+            }
+            statements.push(BatchEntry {
+                label: self.labels.get(&i).cloned(),
+                kind: StatementKind::of(&code),
+                index: statements.len(),
+            });
+        }
+        IndexedBatch {
+            script: self.build(),
+            statements,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Condition;
+
+    #[test]
+    fn batch_indices_follow_statements() {
+        let mut script = ScriptBuilder::new();
+        script
+            .comment("totals")
+            .let_raw("n", "count(SELECT * FROM post)")
+            .returning(vec![("posts", "$n")]);
+        let mut users = QueryBuilder::new();
+        users
+            .from("user")
+            .where_complex(Condition::eq("active", true));
+
+        let batch = BatchBuilder::new()
+            .add_statement("USE NS app DB main")
+            .add_query(&users)
+            .unwrap()
+            .label("users")
+            .add_script(&script)
+            .unwrap()
+            .label("totals")
+            .add_statement("INFO FOR DB")
+            .build_indexed();
+
+        assert_eq!(batch.index_of("users"), Some(1));
+        assert_eq!(batch.index_of("totals"), Some(3));
+        assert_eq!(batch.statements.len(), 5);
+        assert_eq!(batch.statements[4].kind, StatementKind::Info);
+        assert_eq!(batch.index_of("missing"), None);
+        assert!(batch.script.starts_with(
+            "USE NS app DB main;\nSELECT * FROM user WHERE active = true;\n-- totals\n"
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+//! Control-flow statements for generated scripts.
+
+use crate::{
+    BuildError, Condition, QueryBuilder, ScriptBuilder, StatementKind, Value, render_condition,
+    terminate,
+};
+
+/// Builder for `FOR $item IN <iterable> { ... }` loops.
+///
+/// ```
+/// use surrealex::{ForLoopBuilder, param};
+///
+/// let mut lp = ForLoopBuilder::over("user", param("users"));
+/// lp.statement("UPDATE $user SET active = true");
+/// assert_eq!(
+///     lp.build(),
+///     "FOR $user IN $users { UPDATE $user SET active = true; };"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForLoopBuilder {
+    name: String,
+    iterable: String,
+    body: Vec<String>,
+}
+
+impl ForLoopBuilder {
+    /// Loops over `iterable`, e.g. an array literal or a parameter, binding
+    /// each item to `$name`; the leading `$` is optional.
+    pub fn over(name: &str, iterable: impl Into<Value>) -> Self {
+        Self {
+            name: name.trim_start_matches('$').to_string(),
+            iterable: iterable.into().to_surql(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Loops over the results of a subquery. Returns Err if the query
+    /// cannot be built.
+    pub fn over_query(name: &str, qb: &QueryBuilder) -> Result<Self, BuildError> {
+        Ok(Self {
+            name: name.trim_start_matches('$').to_string(),
+            iterable: format!("({})", qb.build()?),
+            body: Vec::new(),
+        })
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::For
+    }
+
+    /// Add a raw statement to the body (terminated with a semicolon if
+    /// missing).
+    pub fn statement(&mut self, stmt: &str) -> &mut Self {
+        self.body.push(terminate(stmt));
+        self
+    }
+
+    /// Add a `QueryBuilder`'s built query to the body.
+    pub fn query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.statement(&q))
+    }
+
+    /// Add every statement of a `ScriptBuilder` to the body. The script's
+    /// comments are dropped and its bindings are not carried over.
+    pub fn script(&mut self, script: &ScriptBuilder) -> Result<&mut Self, BuildError> {
+        for stmt in script.code_statements()? {
+            self.statement(&stmt);
+        }
+        Ok(self)
+    }
+
+    /// Add `BREAK`, leaving the loop.
+    pub fn r#break(&mut self) -> &mut Self {
+        self.statement("BREAK")
+    }
+
+    /// Add `CONTINUE`, skipping to the next item.
+    pub fn r#continue(&mut self) -> &mut Self {
+        self.statement("CONTINUE")
+    }
+
+    /// Add `IF <condition> { BREAK; }`.
+    pub fn break_if(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        let condition = render_condition(&condition.into());
+        self.statement(&format!("IF {} {{ BREAK; }}", condition))
+    }
+
+    /// Add `IF <condition> { CONTINUE; }`.
+    pub fn continue_if(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        let condition = render_condition(&condition.into());
+        self.statement(&format!("IF {} {{ CONTINUE; }}", condition))
+    }
+
+    /// Nest another loop in the body.
+    pub fn for_loop(&mut self, inner: &ForLoopBuilder) -> &mut Self {
+        self.body.push(inner.build());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let body = if self.body.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", self.body.join(" "))
+        };
+        format!("FOR ${} IN {} {};", self.name, self.iterable, body)
+    }
+}
+
+/// Renders `THROW <error>`, aborting the script or transaction. Strings
+/// become quoted messages; use [`crate::param`] to rethrow a variable.
+pub fn throw(error: impl Into<Value>) -> String {
+    format!("THROW {}", error.into().to_surql())
+}
+
+/// Builder for a standalone `RETURN <expr>` statement, at the top level of
+/// a request or at the end of a transaction (before `commit()`).
+///
+/// ```
+/// use surrealex::{QueryBuilder, ReturnBuilder, TransactionBuilder};
+///
+/// let mut tb = TransactionBuilder::new();
+/// tb.begin()
+///     .add_statement("LET $u = (CREATE user SET name = 'a')")
+///     .add_statement(&ReturnBuilder::expr("$u.id").build())
+///     .commit();
+/// assert!(tb.build().contains("\nRETURN $u.id;\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReturnBuilder {
+    expr: String,
+    fetch_clauses: Vec<String>,
+}
+
+impl ReturnBuilder {
+    /// Returns a literal value.
+    pub fn value(value: impl Into<Value>) -> Self {
+        Self::expr(&value.into().to_surql())
+    }
+
+    /// Returns a raw expression, e.g. `$user.id` or `count($items)`.
+    pub fn expr(expr: &str) -> Self {
+        Self {
+            expr: expr.to_string(),
+            fetch_clauses: Vec::new(),
+        }
+    }
+
+    /// Returns the result of a subquery. Returns Err if the query cannot be
+    /// built.
+    pub fn query(qb: &QueryBuilder) -> Result<Self, BuildError> {
+        Ok(Self::expr(&format!("({})", qb.build()?)))
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Return
+    }
+
+    /// Adds a field to the FETCH clause. Can be called multiple times.
+    pub fn fetch(&mut self, field: &str) -> &mut Self {
+        self.fetch_clauses.push(field.to_string());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = format!("RETURN {}", self.expr);
+        if !self.fetch_clauses.is_empty() {
+            out.push_str(" FETCH ");
+            out.push_str(&self.fetch_clauses.join(", "));
+        }
+        out
+    }
+}
+
+impl ScriptBuilder {
+    /// Add a THROW statement, see [`throw`].
+    pub fn throw(&mut self, error: impl Into<Value>) -> &mut Self {
+        self.statements.push(terminate(&throw(error)));
+        self
+    }
+
+    /// Assign the result of a block scope built from another script:
+    /// `LET $x = { LET $a = ...; RETURN { ... } };`. The inner script's
+    /// variables stay local to the block, its comments are dropped and its
+    /// bindings are merged into this script's. Returns Err if the inner
+    /// script cannot be built.
+    pub fn let_block(
+        &mut self,
+        name: &str,
+        block: &ScriptBuilder,
+    ) -> Result<&mut Self, BuildError> {
+        let body = block.code_statements()?.join(" ");
+        let body = body.strip_suffix(';').unwrap_or(&body);
+        self.statements.push(format!(
+            "LET ${} = {{ {} }};",
+            name.trim_start_matches('$'),
+            body
+        ));
+        self.exprs.extend(block.exprs.iter().cloned());
+        for (key, value) in block.bindings() {
+            self.bindings
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        Ok(self)
+    }
+
+    /// Add a FOR loop. Loops are never read-only, so `read_only()` scripts
+    /// containing one fail to build.
+    pub fn for_loop(&mut self, lp: &ForLoopBuilder) -> &mut Self {
+        let s = lp.build();
+        self.statements.push(s.clone());
+        self.exprs.push(s);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Condition;
+
+    #[test]
+    fn for_loop_over_subquery_and_array() {
+        let mut stale = QueryBuilder::new();
+        stale
+            .select("VALUE id", None)
+            .from("session")
+            .where_complex(Condition::lt("expires", crate::Raw::new("time::now()")));
+        let mut inner = ForLoopBuilder::over("tag", vec!["a", "b"]);
+        inner.statement("RELATE $s->tagged->tag:[$tag]");
+        let sql = ForLoopBuilder::over_query("s", &stale)
+            .unwrap()
+            .statement("DELETE $s")
+            .for_loop(&inner)
+            .build();
+        assert_eq!(
+            sql,
+            "FOR $s IN (SELECT VALUE id FROM session WHERE expires < time::now()) \
+             { DELETE $s; FOR $tag IN ['a', 'b'] { RELATE $s->tagged->tag:[$tag]; }; };"
+        );
+
+        let mut script = ScriptBuilder::new();
+        script
+            .read_only()
+            .for_loop(&ForLoopBuilder::over("x", vec![1, 2]));
+        assert!(script.build().is_err());
+
+        let mut body = ScriptBuilder::new();
+        body.comment("note").let_raw("a", "SELECT * FROM $x");
+        let sql = ForLoopBuilder::over("x", crate::param("xs"))
+            .script(&body)
+            .unwrap()
+            .build();
+        assert_eq!(sql, "FOR $x IN $xs { LET $a = (SELECT * FROM $x); };");
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let sql = ForLoopBuilder::over("u", crate::param("users"))
+            .continue_if(Condition::raw("$u.deleted"))
+            .break_if(crate::Raw::new("$u.age > 100"))
+            .statement("UPDATE $u SET checked = true")
+            .r#break()
+            .build();
+        assert_eq!(
+            sql,
+            "FOR $u IN $users { IF $u.deleted { CONTINUE; }; IF $u.age > 100 { BREAK; }; \
+             UPDATE $u SET checked = true; BREAK; };"
+        );
+        assert_eq!(
+            ForLoopBuilder::over("x", vec![1]).r#continue().build(),
+            "FOR $x IN [1] { CONTINUE; };"
+        );
+    }
+
+    #[test]
+    fn return_statements() {
+        let mut latest = QueryBuilder::new();
+        latest.from("post").order_by("created DESC").limit(1);
+        assert_eq!(
+            ReturnBuilder::query(&latest)
+                .unwrap()
+                .fetch("author")
+                .build(),
+            "RETURN (SELECT * FROM post ORDER BY created DESC LIMIT 1) FETCH author"
+        );
+        assert_eq!(ReturnBuilder::value(vec![1, 2]).build(), "RETURN [1, 2]");
+        assert_eq!(
+            ReturnBuilder::expr("array::len($items)").kind(),
+            StatementKind::Return
+        );
+    }
+
+    #[test]
+    fn throw_messages_and_params() {
+        assert_eq!(throw("it's broken"), "THROW 'it\\'s broken'");
+        assert_eq!(throw(crate::param("err")), "THROW $err");
+
+        let mut script = ScriptBuilder::new();
+        script
+            .let_raw("n", "count(SELECT * FROM user)")
+            .throw("too many users");
+        assert_eq!(
+            script.build().unwrap(),
+            "LET $n = (count(SELECT * FROM user));\nTHROW 'too many users';"
+        );
+    }
+
+    #[test]
+    fn let_block_scopes() {
+        let mut inner = ScriptBuilder::new();
+        inner
+            .comment("local to the block")
+            .let_raw("a", "SELECT VALUE price FROM item")
+            .bind("rate", 2)
+            .returning(vec![("total", "math::sum($a) * $rate")]);
+        let mut script = ScriptBuilder::new();
+        script
+            .read_only()
+            .let_block("x", &inner)
+            .unwrap()
+            .returning(vec![("x", "$x")]);
+        assert_eq!(
+            script.build().unwrap(),
+            "LET $x = { LET $a = (SELECT VALUE price FROM item); \
+             RETURN { total: math::sum($a) * $rate } };\nRETURN { x: $x }; "
+        );
+        assert_eq!(script.bindings().len(), 1);
+
+        let mut write = ScriptBuilder::new();
+        write.let_raw("u", "CREATE user");
+        assert!(script.let_block("y", &write).unwrap().build().is_err());
+    }
+}
@@ -0,0 +1,2196 @@
+//! Builders for DEFINE, REMOVE and INFO statements, so schema setup,
+//! teardown and introspection are generated with the same crate that builds
+//! the queries against them.
+
+use std::time::Duration;
+
+use crate::{
+    BuildError, Condition, Distance, QueryBuilder, ScriptBuilder, StatementKind, Value,
+    escape_ident, quote_string, render_condition, render_duration, terminate,
+};
+
+/// A table action covered by a permission rule or probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionAction {
+    /// `FOR select`
+    Select,
+    /// `FOR create`
+    Create,
+    /// `FOR update`
+    Update,
+    /// `FOR delete`
+    Delete,
+}
+
+impl PermissionAction {
+    pub(crate) fn as_surql(self) -> &'static str {
+        match self {
+            PermissionAction::Select => "select",
+            PermissionAction::Create => "create",
+            PermissionAction::Update => "update",
+            PermissionAction::Delete => "delete",
+        }
+    }
+}
+
+/// What a single `FOR <actions>` permission rule allows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Permission {
+    /// `NONE`: the actions are denied.
+    None,
+    /// `FULL`: the actions are always allowed.
+    Full,
+    /// `WHERE <condition>`: the actions are allowed on matching records.
+    Where(Condition),
+}
+
+impl From<Condition> for Permission {
+    fn from(condition: Condition) -> Self {
+        Permission::Where(condition)
+    }
+}
+
+/// A `PERMISSIONS` clause, accepted by the DEFINE builders.
+///
+/// ```
+/// use surrealex::{Condition, Permission, Permissions, Raw};
+/// use surrealex::PermissionAction::*;
+///
+/// let rules = Permissions::new()
+///     .allow(&[Select], Permission::Full)
+///     .allow(&[Update, Delete], Condition::eq("owner", Raw::new("$auth.id")));
+/// assert_eq!(
+///     rules.to_surql(),
+///     "FOR select FULL FOR update, delete WHERE owner = $auth.id"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Permissions {
+    /// `NONE`: every action is denied.
+    None,
+    /// `FULL`: every action is allowed.
+    Full,
+    /// Per-action rules; actions without a rule are denied.
+    For(Vec<(Vec<PermissionAction>, Permission)>),
+    /// A hand-written clause, rendered verbatim.
+    Raw(String),
+}
+
+impl Permissions {
+    /// Starts an empty set of per-action rules.
+    pub fn new() -> Self {
+        Permissions::For(Vec::new())
+    }
+
+    /// Adds a rule for `actions`. Calling this on `None`, `Full` or `Raw`
+    /// replaces them with per-action rules.
+    pub fn allow(self, actions: &[PermissionAction], permission: impl Into<Permission>) -> Self {
+        let mut rules = match self {
+            Permissions::For(rules) => rules,
+            _ => Vec::new(),
+        };
+        rules.push((actions.to_vec(), permission.into()));
+        Permissions::For(rules)
+    }
+
+    /// Render the clause without the `PERMISSIONS` keyword.
+    pub fn to_surql(&self) -> String {
+        match self {
+            Permissions::None => "NONE".to_string(),
+            Permissions::Full => "FULL".to_string(),
+            Permissions::For(rules) if rules.is_empty() => "NONE".to_string(),
+            Permissions::For(rules) => rules
+                .iter()
+                .map(|(actions, permission)| {
+                    let actions: Vec<&str> = actions.iter().map(|a| a.as_surql()).collect();
+                    let permission = match permission {
+                        Permission::None => "NONE".to_string(),
+                        Permission::Full => "FULL".to_string(),
+                        Permission::Where(condition) => {
+                            format!("WHERE {}", render_condition(condition))
+                        }
+                    };
+                    format!("FOR {} {}", actions.join(", "), permission)
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Permissions::Raw(sql) => sql.clone(),
+        }
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for Permissions {
+    fn from(sql: &str) -> Self {
+        Permissions::Raw(sql.to_string())
+    }
+}
+
+impl From<crate::Raw> for Permissions {
+    fn from(raw: crate::Raw) -> Self {
+        Permissions::Raw(raw.0)
+    }
+}
+
+/// The `TYPE` of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableType {
+    /// `TYPE ANY`, allowing both normal records and relations.
+    Any,
+    /// `TYPE NORMAL`, rejecting relations.
+    Normal,
+    /// `TYPE RELATION IN a | b OUT c`; empty lists leave that side open.
+    Relation {
+        /// Tables allowed as the `in` side.
+        from: Vec<String>,
+        /// Tables allowed as the `out` side.
+        to: Vec<String>,
+    },
+}
+
+impl TableType {
+    /// A relation from `from` records to `to` records.
+    pub fn relation(from: &str, to: &str) -> Self {
+        TableType::Relation {
+            from: vec![from.to_string()],
+            to: vec![to.to_string()],
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            TableType::Any => "TYPE ANY".to_string(),
+            TableType::Normal => "TYPE NORMAL".to_string(),
+            TableType::Relation { from, to } => {
+                let mut out = "TYPE RELATION".to_string();
+                if !from.is_empty() {
+                    out.push_str(&format!(" IN {}", join_tables(from)));
+                }
+                if !to.is_empty() {
+                    out.push_str(&format!(" OUT {}", join_tables(to)));
+                }
+                out
+            }
+        }
+    }
+}
+
+fn join_tables(tables: &[String]) -> String {
+    tables
+        .iter()
+        .map(|t| escape_ident(t))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Builder for `DEFINE TABLE` statements.
+#[derive(Debug, Clone)]
+pub struct DefineTableBuilder {
+    name: String,
+    drop: bool,
+    schemafull: Option<bool>,
+    table_type: Option<TableType>,
+    view: Option<String>,
+    changefeed: Option<Duration>,
+    include_original: bool,
+    permissions: Option<Permissions>,
+}
+
+impl DefineTableBuilder {
+    /// Starts a definition for the table `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            drop: false,
+            schemafull: None,
+            table_type: None,
+            view: None,
+            changefeed: None,
+            include_original: false,
+            permissions: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::table(&self.name)
+    }
+
+    /// Adds `DROP`, so writes to the table are discarded.
+    pub fn drop(&mut self) -> &mut Self {
+        self.drop = true;
+        self
+    }
+
+    /// Adds `SCHEMAFULL`, rejecting fields that are not defined.
+    pub fn schemafull(&mut self) -> &mut Self {
+        self.schemafull = Some(true);
+        self
+    }
+
+    /// Adds `SCHEMALESS`.
+    pub fn schemaless(&mut self) -> &mut Self {
+        self.schemafull = Some(false);
+        self
+    }
+
+    /// Sets the table `TYPE`.
+    pub fn table_type(&mut self, table_type: TableType) -> &mut Self {
+        self.table_type = Some(table_type);
+        self
+    }
+
+    /// Defines the table as a view over `query` (`AS SELECT ...`). Returns
+    /// Err if the query cannot be built.
+    pub fn as_view(&mut self, query: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        self.view = Some(query.build()?);
+        Ok(self)
+    }
+
+    /// Adds `CHANGEFEED <duration>`, keeping changes for that long.
+    pub fn changefeed(&mut self, retention: Duration) -> &mut Self {
+        self.changefeed = Some(retention);
+        self
+    }
+
+    /// Adds `INCLUDE ORIGINAL` to the changefeed, so changes also carry the
+    /// record as it was before. Has no effect without `changefeed()`.
+    pub fn include_original(&mut self) -> &mut Self {
+        self.include_original = true;
+        self
+    }
+
+    /// Adds a `PERMISSIONS` clause, e.g. `NONE`, `FULL` or
+    /// `FOR select WHERE published = true`.
+    pub fn permissions(&mut self, permissions: impl Into<Permissions>) -> &mut Self {
+        self.permissions = Some(permissions.into());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = format!("DEFINE TABLE {}", escape_ident(&self.name));
+        if self.drop {
+            out.push_str(" DROP");
+        }
+        match self.schemafull {
+            Some(true) => out.push_str(" SCHEMAFULL"),
+            Some(false) => out.push_str(" SCHEMALESS"),
+            None => {}
+        }
+        if let Some(table_type) = &self.table_type {
+            out.push(' ');
+            out.push_str(&table_type.render());
+        }
+        if let Some(view) = &self.view {
+            out.push_str(&format!(" AS {}", view));
+        }
+        if let Some(retention) = self.changefeed {
+            out.push_str(&format!(" CHANGEFEED {}", render_duration(retention)));
+            if self.include_original {
+                out.push_str(" INCLUDE ORIGINAL");
+            }
+        }
+        if let Some(permissions) = &self.permissions {
+            out.push_str(&format!(" PERMISSIONS {}", permissions.to_surql()));
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Renders a possibly nested field name such as `address.city` or `tags.*`,
+/// escaping each segment.
+pub(crate) fn render_field_name(name: &str) -> String {
+    name.split('.')
+        .map(|part| match part {
+            "*" => "*".to_string(),
+            part => escape_ident(part),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Builder for `DEFINE FIELD` statements.
+#[derive(Debug, Clone)]
+pub struct DefineFieldBuilder {
+    name: String,
+    table: String,
+    flexible: bool,
+    field_type: Option<String>,
+    default: Option<Value>,
+    readonly: bool,
+    value: Option<Value>,
+    assert: Option<Condition>,
+    permissions: Option<Permissions>,
+}
+
+impl DefineFieldBuilder {
+    /// Starts a definition for the field `name` (dots separate nested
+    /// fields) on `table`.
+    pub fn new(name: &str, table: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            table: table.to_string(),
+            flexible: false,
+            field_type: None,
+            default: None,
+            readonly: false,
+            value: None,
+            assert: None,
+            permissions: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::field(&self.name, &self.table)
+    }
+
+    /// Sets the field `TYPE`, e.g. `string` or `option<record<user>>`.
+    pub fn r#type(&mut self, field_type: &str) -> &mut Self {
+        self.field_type = Some(field_type.to_string());
+        self
+    }
+
+    /// Adds `FLEXIBLE`, allowing schemaless objects in a schemafull table.
+    pub fn flexible(&mut self) -> &mut Self {
+        self.flexible = true;
+        self
+    }
+
+    /// Sets the `DEFAULT` value. Use [`crate::Raw`] for expressions such as
+    /// `time::now()`.
+    pub fn default(&mut self, value: impl Into<Value>) -> &mut Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Adds `READONLY`, so the field cannot change after creation.
+    pub fn readonly(&mut self) -> &mut Self {
+        self.readonly = true;
+        self
+    }
+
+    /// Sets the `VALUE` computed on every write. Use [`crate::Raw`] for
+    /// expressions.
+    pub fn value(&mut self, value: impl Into<Value>) -> &mut Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    /// Sets the `ASSERT` condition, typically about `$value`.
+    pub fn assert(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        self.assert = Some(condition.into());
+        self
+    }
+
+    /// Adds a `PERMISSIONS` clause.
+    pub fn permissions(&mut self, permissions: impl Into<Permissions>) -> &mut Self {
+        self.permissions = Some(permissions.into());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = format!(
+            "DEFINE FIELD {} ON TABLE {}",
+            render_field_name(&self.name),
+            escape_ident(&self.table)
+        );
+        if self.flexible {
+            out.push_str(" FLEXIBLE");
+        }
+        if let Some(field_type) = &self.field_type {
+            out.push_str(&format!(" TYPE {}", field_type));
+        }
+        if let Some(default) = &self.default {
+            out.push_str(&format!(" DEFAULT {}", default.to_surql()));
+        }
+        if self.readonly {
+            out.push_str(" READONLY");
+        }
+        if let Some(value) = &self.value {
+            out.push_str(&format!(" VALUE {}", value.to_surql()));
+        }
+        if let Some(assert) = &self.assert {
+            out.push_str(&format!(" ASSERT {}", render_condition(assert)));
+        }
+        if let Some(permissions) = &self.permissions {
+            out.push_str(&format!(" PERMISSIONS {}", permissions.to_surql()));
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// The kind of index created by [`DefineIndexBuilder`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexType {
+    /// `UNIQUE`
+    Unique,
+    /// `SEARCH ANALYZER name BM25 [HIGHLIGHTS]`, a full-text index.
+    Search {
+        /// The analyzer tokenizing the indexed text.
+        analyzer: String,
+        /// BM25 `(k1, b)` parameters; `None` uses the server defaults.
+        bm25: Option<(f32, f32)>,
+        /// Whether to store the offsets needed by `search::highlight()`.
+        highlights: bool,
+    },
+    /// `MTREE DIMENSION n [DIST metric]`, a vector index.
+    MTree {
+        /// Number of vector dimensions.
+        dimension: u32,
+        /// Distance metric; `None` uses the server default.
+        distance: Option<Distance>,
+    },
+    /// `HNSW DIMENSION n [DIST metric]`, an approximate vector index.
+    Hnsw {
+        /// Number of vector dimensions.
+        dimension: u32,
+        /// Distance metric; `None` uses the server default.
+        distance: Option<Distance>,
+    },
+}
+
+impl IndexType {
+    /// A full-text index using `analyzer`, with default BM25 scoring and
+    /// highlights enabled.
+    pub fn search(analyzer: &str) -> Self {
+        IndexType::Search {
+            analyzer: analyzer.to_string(),
+            bm25: None,
+            highlights: true,
+        }
+    }
+
+    /// An M-tree vector index over `dimension`-sized vectors.
+    pub fn mtree(dimension: u32, distance: Distance) -> Self {
+        IndexType::MTree {
+            dimension,
+            distance: Some(distance),
+        }
+    }
+
+    /// An HNSW vector index over `dimension`-sized vectors.
+    pub fn hnsw(dimension: u32, distance: Distance) -> Self {
+        IndexType::Hnsw {
+            dimension,
+            distance: Some(distance),
+        }
+    }
+
+    fn render(&self) -> String {
+        let vector = |name: &str, dimension: &u32, distance: &Option<Distance>| {
+            let mut out = format!("{} DIMENSION {}", name, dimension);
+            if let Some(distance) = distance {
+                out.push_str(&format!(" DIST {}", distance.as_surql()));
+            }
+            out
+        };
+        match self {
+            IndexType::Unique => "UNIQUE".to_string(),
+            IndexType::Search {
+                analyzer,
+                bm25,
+                highlights,
+            } => {
+                let mut out = format!("SEARCH ANALYZER {} BM25", escape_ident(analyzer));
+                if let Some((k1, b)) = bm25 {
+                    out.push_str(&format!("({}, {})", k1, b));
+                }
+                if *highlights {
+                    out.push_str(" HIGHLIGHTS");
+                }
+                out
+            }
+            IndexType::MTree {
+                dimension,
+                distance,
+            } => vector("MTREE", dimension, distance),
+            IndexType::Hnsw {
+                dimension,
+                distance,
+            } => vector("HNSW", dimension, distance),
+        }
+    }
+}
+
+/// Builder for `DEFINE INDEX` statements.
+#[derive(Debug, Clone)]
+pub struct DefineIndexBuilder {
+    name: String,
+    table: String,
+    fields: Vec<String>,
+    index_type: Option<IndexType>,
+}
+
+impl DefineIndexBuilder {
+    /// Starts a definition for the index `name` on `table`.
+    pub fn new(name: &str, table: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            table: table.to_string(),
+            fields: Vec::new(),
+            index_type: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::index(&self.name, &self.table)
+    }
+
+    /// Adds an indexed field. Fields render in the order they were added.
+    pub fn field(&mut self, field: &str) -> &mut Self {
+        self.fields.push(field.to_string());
+        self
+    }
+
+    /// Adds several indexed fields.
+    pub fn fields(&mut self, fields: &[&str]) -> &mut Self {
+        self.fields.extend(fields.iter().map(|f| f.to_string()));
+        self
+    }
+
+    /// Makes the index `UNIQUE`.
+    pub fn unique(&mut self) -> &mut Self {
+        self.index_type = Some(IndexType::Unique);
+        self
+    }
+
+    /// Sets the index type; a plain index is created when none is set.
+    pub fn index_type(&mut self, index_type: IndexType) -> &mut Self {
+        self.index_type = Some(index_type);
+        self
+    }
+
+    /// Build the statement. Returns Err if no field was added.
+    pub fn build(&self) -> Result<String, BuildError> {
+        if self.fields.is_empty() {
+            return Err(BuildError::new("An index needs at least one field.")
+                .with_suggestion("call .field(name) before .build()"));
+        }
+        let fields: Vec<String> = self.fields.iter().map(|f| render_field_name(f)).collect();
+        let mut out = format!(
+            "DEFINE INDEX {} ON TABLE {} FIELDS {}",
+            escape_ident(&self.name),
+            escape_ident(&self.table),
+            fields.join(", ")
+        );
+        if let Some(index_type) = &self.index_type {
+            out.push(' ');
+            out.push_str(&index_type.render());
+        }
+        out.push(';');
+        Ok(out)
+    }
+}
+
+/// A tokenizer splitting text for a [`DefineAnalyzerBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// `blank`: splits on whitespace.
+    Blank,
+    /// `camel`: splits on camelCase boundaries.
+    Camel,
+    /// `class`: splits where the character class changes.
+    Class,
+    /// `punct`: splits on punctuation.
+    Punct,
+}
+
+impl Tokenizer {
+    fn as_surql(self) -> &'static str {
+        match self {
+            Tokenizer::Blank => "blank",
+            Tokenizer::Camel => "camel",
+            Tokenizer::Class => "class",
+            Tokenizer::Punct => "punct",
+        }
+    }
+}
+
+/// Language of the `snowball` stemming filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// `arabic`
+    Arabic,
+    /// `danish`
+    Danish,
+    /// `dutch`
+    Dutch,
+    /// `english`
+    English,
+    /// `french`
+    French,
+    /// `german`
+    German,
+    /// `greek`
+    Greek,
+    /// `hungarian`
+    Hungarian,
+    /// `norwegian`
+    Norwegian,
+    /// `portuguese`
+    Portuguese,
+    /// `romanian`
+    Romanian,
+    /// `russian`
+    Russian,
+    /// `spanish`
+    Spanish,
+    /// `swedish`
+    Swedish,
+    /// `tamil`
+    Tamil,
+    /// `turkish`
+    Turkish,
+}
+
+impl Language {
+    fn as_surql(self) -> &'static str {
+        match self {
+            Language::Arabic => "arabic",
+            Language::Danish => "danish",
+            Language::Dutch => "dutch",
+            Language::English => "english",
+            Language::French => "french",
+            Language::German => "german",
+            Language::Greek => "greek",
+            Language::Hungarian => "hungarian",
+            Language::Norwegian => "norwegian",
+            Language::Portuguese => "portuguese",
+            Language::Romanian => "romanian",
+            Language::Russian => "russian",
+            Language::Spanish => "spanish",
+            Language::Swedish => "swedish",
+            Language::Tamil => "tamil",
+            Language::Turkish => "turkish",
+        }
+    }
+}
+
+/// A filter applied to tokens by a [`DefineAnalyzerBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalyzerFilter {
+    /// `ascii`: folds accented characters to ASCII.
+    Ascii,
+    /// `lowercase`
+    Lowercase,
+    /// `uppercase`
+    Uppercase,
+    /// `edgengram(min, max)`: prefixes of each token.
+    EdgeNgram(u16, u16),
+    /// `ngram(min, max)`: substrings of each token.
+    Ngram(u16, u16),
+    /// `snowball(language)`: stems tokens.
+    Snowball(Language),
+    /// `mapper('path')`: replaces tokens using a mapping file.
+    Mapper(String),
+}
+
+impl AnalyzerFilter {
+    fn render(&self) -> String {
+        match self {
+            AnalyzerFilter::Ascii => "ascii".to_string(),
+            AnalyzerFilter::Lowercase => "lowercase".to_string(),
+            AnalyzerFilter::Uppercase => "uppercase".to_string(),
+            AnalyzerFilter::EdgeNgram(min, max) => format!("edgengram({}, {})", min, max),
+            AnalyzerFilter::Ngram(min, max) => format!("ngram({}, {})", min, max),
+            AnalyzerFilter::Snowball(language) => format!("snowball({})", language.as_surql()),
+            AnalyzerFilter::Mapper(path) => format!("mapper({})", quote_string(path)),
+        }
+    }
+}
+
+/// Builder for `DEFINE ANALYZER` statements, used by full-text
+/// [`IndexType::Search`] indexes.
+#[derive(Debug, Clone)]
+pub struct DefineAnalyzerBuilder {
+    name: String,
+    function: Option<String>,
+    tokenizers: Vec<Tokenizer>,
+    filters: Vec<AnalyzerFilter>,
+}
+
+impl DefineAnalyzerBuilder {
+    /// Starts a definition for the analyzer `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            function: None,
+            tokenizers: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::analyzer(&self.name)
+    }
+
+    /// Runs the text through a custom function first, e.g. `fn::strip_html`.
+    pub fn function(&mut self, function: &str) -> &mut Self {
+        self.function = Some(function.to_string());
+        self
+    }
+
+    /// Adds a tokenizer. Tokenizers run in the order they were added.
+    pub fn tokenizer(&mut self, tokenizer: Tokenizer) -> &mut Self {
+        self.tokenizers.push(tokenizer);
+        self
+    }
+
+    /// Adds a filter. Filters run in the order they were added.
+    pub fn filter(&mut self, filter: AnalyzerFilter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = format!("DEFINE ANALYZER {}", escape_ident(&self.name));
+        if let Some(function) = &self.function {
+            out.push_str(&format!(" FUNCTION {}", function));
+        }
+        if !self.tokenizers.is_empty() {
+            let tokenizers: Vec<&str> = self.tokenizers.iter().map(|t| t.as_surql()).collect();
+            out.push_str(&format!(" TOKENIZERS {}", tokenizers.join(",")));
+        }
+        if !self.filters.is_empty() {
+            let filters: Vec<String> = self.filters.iter().map(|f| f.render()).collect();
+            out.push_str(&format!(" FILTERS {}", filters.join(",")));
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Builder for `DEFINE EVENT` statements, the table triggers that run a
+/// block of statements when a record changes.
+#[derive(Debug, Clone)]
+pub struct DefineEventBuilder {
+    name: String,
+    table: String,
+    when: Option<Condition>,
+    then: Vec<String>,
+}
+
+impl DefineEventBuilder {
+    /// Starts a definition for the event `name` on `table`.
+    pub fn new(name: &str, table: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            table: table.to_string(),
+            when: None,
+            then: Vec::new(),
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::event(&self.name, &self.table)
+    }
+
+    /// Only fire when the condition holds, e.g. `$event = "UPDATE"`. Without
+    /// it the event fires on every change.
+    pub fn when(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        self.when = Some(condition.into());
+        self
+    }
+
+    /// Add a raw statement to the THEN block (terminated with a semicolon if
+    /// missing).
+    pub fn then_statement(&mut self, stmt: &str) -> &mut Self {
+        self.then.push(terminate(stmt));
+        self
+    }
+
+    /// Add a `QueryBuilder`'s built query to the THEN block.
+    pub fn then_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.then_statement(&q))
+    }
+
+    /// Add every statement of a `ScriptBuilder` to the THEN block. The
+    /// script's comments are dropped and its bindings are not carried over.
+    pub fn then_script(&mut self, script: &ScriptBuilder) -> Result<&mut Self, BuildError> {
+        for stmt in script.code_statements()? {
+            self.then_statement(&stmt);
+        }
+        Ok(self)
+    }
+
+    /// Build the statement. Returns Err if the THEN block is empty.
+    pub fn build(&self) -> Result<String, BuildError> {
+        if self.then.is_empty() {
+            return Err(
+                BuildError::new("An event needs at least one THEN statement.").with_suggestion(
+                    "call .then_statement(...) or .then_query(...) before .build()",
+                ),
+            );
+        }
+        let mut out = format!(
+            "DEFINE EVENT {} ON TABLE {}",
+            escape_ident(&self.name),
+            escape_ident(&self.table)
+        );
+        if let Some(when) = &self.when {
+            out.push_str(&format!(" WHEN {}", render_condition(when)));
+        }
+        out.push_str(&format!(" THEN {{ {} }};", self.then.join(" ")));
+        Ok(out)
+    }
+}
+
+/// Builder for `DEFINE FUNCTION` statements.
+#[derive(Debug, Clone)]
+pub struct DefineFunctionBuilder {
+    name: String,
+    args: Vec<(String, String)>,
+    returns: Option<String>,
+    body: Vec<String>,
+    permissions: Option<Permissions>,
+}
+
+impl DefineFunctionBuilder {
+    /// Starts a definition for the function `name`; the `fn::` prefix is
+    /// optional.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.trim_start_matches("fn::").to_string(),
+            args: Vec::new(),
+            returns: None,
+            body: Vec::new(),
+            permissions: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::function(&self.name)
+    }
+
+    /// Adds an argument, e.g. `arg("name", "string")`; a leading `$` on the
+    /// name is optional.
+    pub fn arg(&mut self, name: &str, arg_type: &str) -> &mut Self {
+        self.args.push((
+            name.trim_start_matches('$').to_string(),
+            arg_type.to_string(),
+        ));
+        self
+    }
+
+    /// Declares the return type (`-> type`).
+    pub fn returns(&mut self, return_type: &str) -> &mut Self {
+        self.returns = Some(return_type.to_string());
+        self
+    }
+
+    /// Add a raw statement to the body (terminated with a semicolon if
+    /// missing).
+    pub fn body_statement(&mut self, stmt: &str) -> &mut Self {
+        self.body.push(terminate(stmt));
+        self
+    }
+
+    /// Add a `QueryBuilder`'s built query to the body.
+    pub fn body_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.body_statement(&q))
+    }
+
+    /// Add every statement of a `ScriptBuilder` to the body, including its
+    /// RETURN. The script's comments are dropped and its bindings are not
+    /// carried over.
+    pub fn body_script(&mut self, script: &ScriptBuilder) -> Result<&mut Self, BuildError> {
+        for stmt in script.code_statements()? {
+            self.body_statement(&stmt);
+        }
+        Ok(self)
+    }
+
+    /// Adds a `PERMISSIONS` clause.
+    pub fn permissions(&mut self, permissions: impl Into<Permissions>) -> &mut Self {
+        self.permissions = Some(permissions.into());
+        self
+    }
+
+    /// Build the statement. Returns Err if the body is empty.
+    pub fn build(&self) -> Result<String, BuildError> {
+        if self.body.is_empty() {
+            return Err(BuildError::new("A function needs a body.").with_suggestion(
+                "call .body_statement(...) or .body_script(...) before .build()",
+            ));
+        }
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|(name, arg_type)| format!("${}: {}", escape_ident(name), arg_type))
+            .collect();
+        let mut out = format!("DEFINE FUNCTION fn::{}({})", self.name, args.join(", "));
+        if let Some(returns) = &self.returns {
+            out.push_str(&format!(" -> {}", returns));
+        }
+        out.push_str(&format!(" {{ {} }}", self.body.join(" ")));
+        if let Some(permissions) = &self.permissions {
+            out.push_str(&format!(" PERMISSIONS {}", permissions.to_surql()));
+        }
+        out.push(';');
+        Ok(out)
+    }
+}
+
+/// Builder for `DEFINE PARAM` statements, the database-wide `$name`
+/// parameters.
+#[derive(Debug, Clone)]
+pub struct DefineParamBuilder {
+    name: String,
+    value: Value,
+    permissions: Option<Permissions>,
+}
+
+impl DefineParamBuilder {
+    /// Starts a definition for `$name` holding `value`; the leading `$` is
+    /// optional.
+    pub fn new(name: &str, value: impl Into<Value>) -> Self {
+        Self {
+            name: name.trim_start_matches('$').to_string(),
+            value: value.into(),
+            permissions: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::param(&self.name)
+    }
+
+    /// Adds a `PERMISSIONS` clause.
+    pub fn permissions(&mut self, permissions: impl Into<Permissions>) -> &mut Self {
+        self.permissions = Some(permissions.into());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = format!(
+            "DEFINE PARAM ${} VALUE {}",
+            escape_ident(&self.name),
+            self.value.to_surql()
+        );
+        if let Some(permissions) = &self.permissions {
+            out.push_str(&format!(" PERMISSIONS {}", permissions.to_surql()));
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Signing algorithm of a JWT access method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    /// `EDDSA`
+    EdDsa,
+    /// `ES256`
+    Es256,
+    /// `ES384`
+    Es384,
+    /// `ES512`
+    Es512,
+    /// `HS256`
+    Hs256,
+    /// `HS384`
+    Hs384,
+    /// `HS512`
+    Hs512,
+    /// `PS256`
+    Ps256,
+    /// `PS384`
+    Ps384,
+    /// `PS512`
+    Ps512,
+    /// `RS256`
+    Rs256,
+    /// `RS384`
+    Rs384,
+    /// `RS512`
+    Rs512,
+}
+
+impl JwtAlgorithm {
+    fn as_surql(self) -> &'static str {
+        match self {
+            JwtAlgorithm::EdDsa => "EDDSA",
+            JwtAlgorithm::Es256 => "ES256",
+            JwtAlgorithm::Es384 => "ES384",
+            JwtAlgorithm::Es512 => "ES512",
+            JwtAlgorithm::Hs256 => "HS256",
+            JwtAlgorithm::Hs384 => "HS384",
+            JwtAlgorithm::Hs512 => "HS512",
+            JwtAlgorithm::Ps256 => "PS256",
+            JwtAlgorithm::Ps384 => "PS384",
+            JwtAlgorithm::Ps512 => "PS512",
+            JwtAlgorithm::Rs256 => "RS256",
+            JwtAlgorithm::Rs384 => "RS384",
+            JwtAlgorithm::Rs512 => "RS512",
+        }
+    }
+}
+
+/// How tokens of an access method are verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JwtVerification {
+    Key(JwtAlgorithm, String),
+    Url(String),
+}
+
+impl JwtVerification {
+    fn render(&self) -> String {
+        match self {
+            JwtVerification::Key(algorithm, key) => {
+                format!(
+                    "ALGORITHM {} KEY {}",
+                    algorithm.as_surql(),
+                    quote_string(key)
+                )
+            }
+            JwtVerification::Url(url) => format!("URL {}", quote_string(url)),
+        }
+    }
+}
+
+/// Builder for `DEFINE ACCESS` statements, either record access with
+/// SIGNUP/SIGNIN expressions or JWT access.
+#[derive(Debug, Clone)]
+pub struct DefineAccessBuilder {
+    name: String,
+    on_namespace: bool,
+    record: bool,
+    signup: Option<String>,
+    signin: Option<String>,
+    jwt: Option<JwtVerification>,
+    token_duration: Option<Duration>,
+    session_duration: Option<Duration>,
+}
+
+impl DefineAccessBuilder {
+    fn new(name: &str, record: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            on_namespace: false,
+            record,
+            signup: None,
+            signin: None,
+            jwt: None,
+            token_duration: None,
+            session_duration: None,
+        }
+    }
+
+    /// Starts a `TYPE RECORD` access method, letting record users sign up
+    /// and sign in.
+    pub fn record(name: &str) -> Self {
+        Self::new(name, true)
+    }
+
+    /// Starts a `TYPE JWT` access method, accepting externally issued tokens.
+    pub fn jwt(name: &str) -> Self {
+        Self::new(name, false)
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        let mut remove = RemoveBuilder::access(&self.name);
+        if self.on_namespace {
+            remove.on_namespace();
+        }
+        remove
+    }
+
+    /// Defines the access method on the namespace instead of the database.
+    pub fn on_namespace(&mut self) -> &mut Self {
+        self.on_namespace = true;
+        self
+    }
+
+    /// Sets the SIGNUP expression, typically a `CREATE` statement.
+    pub fn signup(&mut self, stmt: &str) -> &mut Self {
+        self.signup = Some(stmt.trim().trim_end_matches(';').to_string());
+        self
+    }
+
+    /// Sets the SIGNUP expression from a `QueryBuilder`.
+    pub fn signup_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.signup(&q))
+    }
+
+    /// Sets the SIGNIN expression, typically a `SELECT` matching the
+    /// credentials.
+    pub fn signin(&mut self, stmt: &str) -> &mut Self {
+        self.signin = Some(stmt.trim().trim_end_matches(';').to_string());
+        self
+    }
+
+    /// Sets the SIGNIN expression from a `QueryBuilder`.
+    pub fn signin_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.signin(&q))
+    }
+
+    /// Verifies tokens with `algorithm` and `key` (a secret or public key).
+    /// On record access this renders as `WITH JWT ...`.
+    pub fn key(&mut self, algorithm: JwtAlgorithm, key: &str) -> &mut Self {
+        self.jwt = Some(JwtVerification::Key(algorithm, key.to_string()));
+        self
+    }
+
+    /// Verifies tokens against the JWKS published at `url`.
+    pub fn url(&mut self, url: &str) -> &mut Self {
+        self.jwt = Some(JwtVerification::Url(url.to_string()));
+        self
+    }
+
+    /// Sets how long issued tokens are valid.
+    pub fn token_duration(&mut self, duration: Duration) -> &mut Self {
+        self.token_duration = Some(duration);
+        self
+    }
+
+    /// Sets how long sessions last.
+    pub fn session_duration(&mut self, duration: Duration) -> &mut Self {
+        self.session_duration = Some(duration);
+        self
+    }
+
+    /// Build the statement. Returns Err for a JWT access method without a
+    /// key or URL, or one with SIGNUP/SIGNIN expressions.
+    pub fn build(&self) -> Result<String, BuildError> {
+        let mut out = format!(
+            "DEFINE ACCESS {} ON {}",
+            escape_ident(&self.name),
+            if self.on_namespace {
+                "NAMESPACE"
+            } else {
+                "DATABASE"
+            }
+        );
+        if self.record {
+            out.push_str(" TYPE RECORD");
+            if let Some(signup) = &self.signup {
+                out.push_str(&format!(" SIGNUP ({})", signup));
+            }
+            if let Some(signin) = &self.signin {
+                out.push_str(&format!(" SIGNIN ({})", signin));
+            }
+            if let Some(jwt) = &self.jwt {
+                out.push_str(&format!(" WITH JWT {}", jwt.render()));
+            }
+        } else {
+            if self.signup.is_some() || self.signin.is_some() {
+                return Err(BuildError::new("SIGNUP and SIGNIN require record access.")
+                    .with_suggestion("use DefineAccessBuilder::record"));
+            }
+            let Some(jwt) = &self.jwt else {
+                return Err(BuildError::new("JWT access requires a key or URL.")
+                    .with_suggestion("call .key(algorithm, key) or .url(url) before .build()"));
+            };
+            out.push_str(&format!(" TYPE JWT {}", jwt.render()));
+        }
+        let durations: Vec<String> = [
+            ("TOKEN", self.token_duration),
+            ("SESSION", self.session_duration),
+        ]
+        .into_iter()
+        .filter_map(|(name, duration)| {
+            duration.map(|d| format!("FOR {} {}", name, render_duration(d)))
+        })
+        .collect();
+        if !durations.is_empty() {
+            out.push_str(&format!(" DURATION {}", durations.join(", ")));
+        }
+        out.push(';');
+        Ok(out)
+    }
+}
+
+/// Renders `DEFINE <what> [IF NOT EXISTS] name [COMMENT '...'];`.
+fn define_named(what: &str, name: &str, if_not_exists: bool, comment: &Option<String>) -> String {
+    let mut out = format!("DEFINE {}", what);
+    if if_not_exists {
+        out.push_str(" IF NOT EXISTS");
+    }
+    out.push(' ');
+    out.push_str(&escape_ident(name));
+    if let Some(comment) = comment {
+        out.push_str(&format!(" COMMENT {}", quote_string(comment)));
+    }
+    out.push(';');
+    out
+}
+
+/// Builder for `DEFINE NAMESPACE` statements.
+#[derive(Debug, Clone)]
+pub struct DefineNamespaceBuilder {
+    name: String,
+    if_not_exists: bool,
+    comment: Option<String>,
+}
+
+impl DefineNamespaceBuilder {
+    /// Starts a definition for the namespace `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            if_not_exists: false,
+            comment: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::namespace(&self.name)
+    }
+
+    /// Adds `IF NOT EXISTS`, so re-running a provisioning script is a no-op.
+    pub fn if_not_exists(&mut self) -> &mut Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Adds a `COMMENT`.
+    pub fn comment(&mut self, comment: &str) -> &mut Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        define_named("NAMESPACE", &self.name, self.if_not_exists, &self.comment)
+    }
+}
+
+/// Builder for `DEFINE DATABASE` statements.
+#[derive(Debug, Clone)]
+pub struct DefineDatabaseBuilder {
+    name: String,
+    if_not_exists: bool,
+    comment: Option<String>,
+}
+
+impl DefineDatabaseBuilder {
+    /// Starts a definition for the database `name` in the current namespace.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            if_not_exists: false,
+            comment: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Define
+    }
+
+    /// The REMOVE statement undoing this definition.
+    pub(crate) fn removal(&self) -> RemoveBuilder {
+        RemoveBuilder::database(&self.name)
+    }
+
+    /// Adds `IF NOT EXISTS`, so re-running a provisioning script is a no-op.
+    pub fn if_not_exists(&mut self) -> &mut Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Adds a `COMMENT`.
+    pub fn comment(&mut self, comment: &str) -> &mut Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        define_named("DATABASE", &self.name, self.if_not_exists, &self.comment)
+    }
+}
+
+/// The schema of a table mapped to a Rust type. Derive it with
+/// `#[derive(SurrealSchema)]` (the `derive` feature) to keep struct fields
+/// and `DEFINE FIELD` statements in sync.
+pub trait SurrealSchema {
+    /// The `DEFINE TABLE` statement.
+    fn define_table() -> DefineTableBuilder;
+
+    /// One `DEFINE FIELD` statement per field, in declaration order.
+    fn define_fields() -> Vec<DefineFieldBuilder>;
+
+    /// The `DEFINE INDEX` statements; none by default.
+    fn define_indexes() -> Vec<DefineIndexBuilder> {
+        Vec::new()
+    }
+
+    /// The table, field and index definitions as one script, one statement
+    /// per line. Returns Err if an index cannot be built.
+    fn schema() -> Result<String, BuildError> {
+        let mut statements = vec![Self::define_table().build()];
+        statements.extend(Self::define_fields().iter().map(|f| f.build()));
+        for index in Self::define_indexes() {
+            statements.push(index.build()?);
+        }
+        Ok(statements.join("\n"))
+    }
+}
+
+/// What a [`RemoveBuilder`] removes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RemoveTarget {
+    Namespace,
+    Database,
+    Table,
+    Field(String),
+    Index(String),
+    Event(String),
+    Function,
+    Param,
+    Analyzer,
+    User,
+    Access,
+}
+
+/// Builder for `REMOVE` statements, the counterpart of the DEFINE builders.
+#[derive(Debug, Clone)]
+pub struct RemoveBuilder {
+    target: RemoveTarget,
+    name: String,
+    if_exists: bool,
+    on: &'static str,
+}
+
+impl RemoveBuilder {
+    fn new(target: RemoveTarget, name: &str) -> Self {
+        Self {
+            target,
+            name: name.to_string(),
+            if_exists: false,
+            on: "DATABASE",
+        }
+    }
+
+    /// `REMOVE NAMESPACE name`
+    pub fn namespace(name: &str) -> Self {
+        Self::new(RemoveTarget::Namespace, name)
+    }
+
+    /// `REMOVE DATABASE name`
+    pub fn database(name: &str) -> Self {
+        Self::new(RemoveTarget::Database, name)
+    }
+
+    /// `REMOVE TABLE name`
+    pub fn table(name: &str) -> Self {
+        Self::new(RemoveTarget::Table, name)
+    }
+
+    /// `REMOVE FIELD name ON TABLE table`
+    pub fn field(name: &str, table: &str) -> Self {
+        Self::new(RemoveTarget::Field(table.to_string()), name)
+    }
+
+    /// `REMOVE INDEX name ON TABLE table`
+    pub fn index(name: &str, table: &str) -> Self {
+        Self::new(RemoveTarget::Index(table.to_string()), name)
+    }
+
+    /// `REMOVE EVENT name ON TABLE table`
+    pub fn event(name: &str, table: &str) -> Self {
+        Self::new(RemoveTarget::Event(table.to_string()), name)
+    }
+
+    /// `REMOVE FUNCTION fn::name`; the `fn::` prefix is optional.
+    pub fn function(name: &str) -> Self {
+        Self::new(RemoveTarget::Function, name.trim_start_matches("fn::"))
+    }
+
+    /// `REMOVE PARAM $name`; the leading `$` is optional.
+    pub fn param(name: &str) -> Self {
+        Self::new(RemoveTarget::Param, name.trim_start_matches('$'))
+    }
+
+    /// `REMOVE ANALYZER name`
+    pub fn analyzer(name: &str) -> Self {
+        Self::new(RemoveTarget::Analyzer, name)
+    }
+
+    /// `REMOVE USER name ON DATABASE`
+    pub fn user(name: &str) -> Self {
+        Self::new(RemoveTarget::User, name)
+    }
+
+    /// `REMOVE ACCESS name ON DATABASE`
+    pub fn access(name: &str) -> Self {
+        Self::new(RemoveTarget::Access, name)
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Remove
+    }
+
+    /// Adds `IF EXISTS`, so removing something already gone is a no-op.
+    pub fn if_exists(&mut self) -> &mut Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Removes a user or access method defined on the namespace instead of
+    /// the database.
+    pub fn on_namespace(&mut self) -> &mut Self {
+        self.on = "NAMESPACE";
+        self
+    }
+
+    /// Removes a root user.
+    pub fn on_root(&mut self) -> &mut Self {
+        self.on = "ROOT";
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let what = match &self.target {
+            RemoveTarget::Namespace => "NAMESPACE",
+            RemoveTarget::Database => "DATABASE",
+            RemoveTarget::Table => "TABLE",
+            RemoveTarget::Field(_) => "FIELD",
+            RemoveTarget::Index(_) => "INDEX",
+            RemoveTarget::Event(_) => "EVENT",
+            RemoveTarget::Function => "FUNCTION",
+            RemoveTarget::Param => "PARAM",
+            RemoveTarget::Analyzer => "ANALYZER",
+            RemoveTarget::User => "USER",
+            RemoveTarget::Access => "ACCESS",
+        };
+        let mut out = format!("REMOVE {}", what);
+        if self.if_exists {
+            out.push_str(" IF EXISTS");
+        }
+        match &self.target {
+            RemoveTarget::Field(_) => {
+                out.push_str(&format!(" {}", render_field_name(&self.name)));
+            }
+            RemoveTarget::Function => out.push_str(&format!(" fn::{}", self.name)),
+            RemoveTarget::Param => out.push_str(&format!(" ${}", escape_ident(&self.name))),
+            _ => out.push_str(&format!(" {}", escape_ident(&self.name))),
+        }
+        match &self.target {
+            RemoveTarget::Field(table)
+            | RemoveTarget::Index(table)
+            | RemoveTarget::Event(table) => {
+                out.push_str(&format!(" ON TABLE {}", escape_ident(table)));
+            }
+            RemoveTarget::User | RemoveTarget::Access => {
+                out.push_str(&format!(" ON {}", self.on));
+            }
+            _ => {}
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// What an [`InfoBuilder`] describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum InfoTarget {
+    Root,
+    Namespace,
+    Database,
+    Table(String),
+    User(String, Option<&'static str>),
+}
+
+/// Builder for `INFO FOR` statements.
+#[derive(Debug, Clone)]
+pub struct InfoBuilder {
+    target: InfoTarget,
+    structure: bool,
+}
+
+impl InfoBuilder {
+    fn new(target: InfoTarget) -> Self {
+        Self {
+            target,
+            structure: false,
+        }
+    }
+
+    /// `INFO FOR ROOT`
+    pub fn root() -> Self {
+        Self::new(InfoTarget::Root)
+    }
+
+    /// `INFO FOR NS`
+    pub fn namespace() -> Self {
+        Self::new(InfoTarget::Namespace)
+    }
+
+    /// `INFO FOR DB`
+    pub fn database() -> Self {
+        Self::new(InfoTarget::Database)
+    }
+
+    /// `INFO FOR TABLE table`
+    pub fn table(table: &str) -> Self {
+        Self::new(InfoTarget::Table(table.to_string()))
+    }
+
+    /// `INFO FOR USER name`, looked up at the current level.
+    pub fn user(name: &str) -> Self {
+        Self::new(InfoTarget::User(name.to_string(), None))
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Info
+    }
+
+    /// Looks a user up on the root level (`ON ROOT`).
+    pub fn on_root(&mut self) -> &mut Self {
+        self.on("ROOT")
+    }
+
+    /// Looks a user up on the namespace (`ON NAMESPACE`).
+    pub fn on_namespace(&mut self) -> &mut Self {
+        self.on("NAMESPACE")
+    }
+
+    /// Looks a user up on the database (`ON DATABASE`).
+    pub fn on_database(&mut self) -> &mut Self {
+        self.on("DATABASE")
+    }
+
+    fn on(&mut self, level: &'static str) -> &mut Self {
+        if let InfoTarget::User(_, on) = &mut self.target {
+            *on = Some(level);
+        }
+        self
+    }
+
+    /// Adds `STRUCTURE`, returning definitions as objects instead of
+    /// SurrealQL strings.
+    pub fn structure(&mut self) -> &mut Self {
+        self.structure = true;
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let mut out = match &self.target {
+            InfoTarget::Root => "INFO FOR ROOT".to_string(),
+            InfoTarget::Namespace => "INFO FOR NS".to_string(),
+            InfoTarget::Database => "INFO FOR DB".to_string(),
+            InfoTarget::Table(table) => format!("INFO FOR TABLE {}", escape_ident(table)),
+            InfoTarget::User(name, on) => {
+                let mut out = format!("INFO FOR USER {}", escape_ident(name));
+                if let Some(on) = on {
+                    out.push_str(&format!(" ON {}", on));
+                }
+                out
+            }
+        };
+        if self.structure {
+            out.push_str(" STRUCTURE");
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Where a [`ShowChangesBuilder`] starts reading.
+#[derive(Debug, Clone, PartialEq)]
+enum Since {
+    Versionstamp(u64),
+    Time(Value),
+}
+
+/// Builder for `SHOW CHANGES` statements, reading a table or database
+/// changefeed.
+#[derive(Debug, Clone)]
+pub struct ShowChangesBuilder {
+    table: Option<String>,
+    since: Option<Since>,
+    limit: Option<u64>,
+}
+
+impl ShowChangesBuilder {
+    /// `SHOW CHANGES FOR TABLE table`
+    pub fn table(table: &str) -> Self {
+        Self {
+            table: Some(table.to_string()),
+            since: None,
+            limit: None,
+        }
+    }
+
+    /// `SHOW CHANGES FOR DATABASE`
+    pub fn database() -> Self {
+        Self {
+            table: None,
+            since: None,
+            limit: None,
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Show
+    }
+
+    /// Reads changes from the given versionstamp, e.g. the last one a
+    /// consumer processed.
+    pub fn since_versionstamp(&mut self, versionstamp: u64) -> &mut Self {
+        self.since = Some(Since::Versionstamp(versionstamp));
+        self
+    }
+
+    /// Reads changes from a point in time, e.g. `Value::datetime(...)`.
+    pub fn since_time(&mut self, time: impl Into<Value>) -> &mut Self {
+        self.since = Some(Since::Time(time.into()));
+        self
+    }
+
+    /// Returns at most `n` changesets.
+    pub fn limit(&mut self, n: u64) -> &mut Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Build the statement. Returns Err if no starting point was set.
+    pub fn build(&self) -> Result<String, BuildError> {
+        let Some(since) = &self.since else {
+            return Err(
+                BuildError::new("SHOW CHANGES requires a SINCE clause.").with_suggestion(
+                    "call .since_versionstamp(...) or .since_time(...) before .build()",
+                ),
+            );
+        };
+        let mut out = match &self.table {
+            Some(table) => format!("SHOW CHANGES FOR TABLE {}", escape_ident(table)),
+            None => "SHOW CHANGES FOR DATABASE".to_string(),
+        };
+        match since {
+            Since::Versionstamp(vs) => out.push_str(&format!(" SINCE {}", vs)),
+            Since::Time(time) => out.push_str(&format!(" SINCE {}", time.to_surql())),
+        }
+        if let Some(limit) = self.limit {
+            out.push_str(&format!(" LIMIT {}", limit));
+        }
+        out.push(';');
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Raw;
+
+    #[test]
+    fn define_table_variants() {
+        let sql = DefineTableBuilder::new("user")
+            .schemafull()
+            .table_type(TableType::Normal)
+            .changefeed(Duration::from_secs(3 * 24 * 3600))
+            .permissions("FOR select FULL FOR create, update, delete NONE")
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE TABLE user SCHEMAFULL TYPE NORMAL CHANGEFEED 3d \
+             PERMISSIONS FOR select FULL FOR create, update, delete NONE;"
+        );
+
+        let relation = DefineTableBuilder::new("likes")
+            .table_type(TableType::relation("user", "post"))
+            .build();
+        assert_eq!(
+            relation,
+            "DEFINE TABLE likes TYPE RELATION IN user OUT post;"
+        );
+        let open = TableType::Relation {
+            from: vec!["user".into(), "bot-account".into()],
+            to: vec![],
+        };
+        assert_eq!(open.render(), "TYPE RELATION IN user | `bot-account`");
+    }
+
+    #[test]
+    fn define_field_clauses() {
+        let sql = DefineFieldBuilder::new("email", "user")
+            .r#type("string")
+            .assert(Raw::new("string::is::email($value)"))
+            .permissions("FOR update WHERE id = $auth.id")
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE FIELD email ON TABLE user TYPE string \
+             ASSERT string::is::email($value) PERMISSIONS FOR update WHERE id = $auth.id;"
+        );
+
+        let sql = DefineFieldBuilder::new("created_at", "user")
+            .r#type("datetime")
+            .default(Raw::new("time::now()"))
+            .readonly()
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE FIELD created_at ON TABLE user TYPE datetime DEFAULT time::now() READONLY;"
+        );
+
+        let sql = DefineFieldBuilder::new("settings.theme", "user-pref")
+            .flexible()
+            .r#type("object")
+            .value("dark")
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE FIELD settings.theme ON TABLE `user-pref` FLEXIBLE TYPE object VALUE 'dark';"
+        );
+        assert_eq!(render_field_name("tags.*"), "tags.*");
+    }
+
+    #[test]
+    fn define_index_types() {
+        let unique = DefineIndexBuilder::new("user_email", "user")
+            .field("email")
+            .unique()
+            .build()
+            .unwrap();
+        assert_eq!(
+            unique,
+            "DEFINE INDEX user_email ON TABLE user FIELDS email UNIQUE;"
+        );
+
+        let search = DefineIndexBuilder::new("post_body", "post")
+            .field("body")
+            .index_type(IndexType::search("english"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            search,
+            "DEFINE INDEX post_body ON TABLE post FIELDS body SEARCH ANALYZER english BM25 HIGHLIGHTS;"
+        );
+        let tuned = IndexType::Search {
+            analyzer: "english".into(),
+            bm25: Some((1.2, 0.75)),
+            highlights: false,
+        };
+        assert_eq!(tuned.render(), "SEARCH ANALYZER english BM25(1.2, 0.75)");
+
+        let vector = DefineIndexBuilder::new("doc_embedding", "doc")
+            .field("embedding")
+            .index_type(IndexType::hnsw(768, Distance::Cosine))
+            .build()
+            .unwrap();
+        assert_eq!(
+            vector,
+            "DEFINE INDEX doc_embedding ON TABLE doc FIELDS embedding HNSW DIMENSION 768 DIST COSINE;"
+        );
+        assert_eq!(
+            IndexType::mtree(3, Distance::Minkowski(2)).render(),
+            "MTREE DIMENSION 3 DIST MINKOWSKI 2"
+        );
+        assert!(DefineIndexBuilder::new("empty", "doc").build().is_err());
+    }
+
+    #[test]
+    fn define_analyzer_pipeline() {
+        let sql = DefineAnalyzerBuilder::new("english")
+            .tokenizer(Tokenizer::Blank)
+            .tokenizer(Tokenizer::Class)
+            .filter(AnalyzerFilter::Lowercase)
+            .filter(AnalyzerFilter::Snowball(Language::English))
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE ANALYZER english TOKENIZERS blank,class FILTERS lowercase,snowball(english);"
+        );
+
+        let sql = DefineAnalyzerBuilder::new("autocomplete")
+            .function("fn::strip_html")
+            .tokenizer(Tokenizer::Camel)
+            .filter(AnalyzerFilter::Ascii)
+            .filter(AnalyzerFilter::EdgeNgram(2, 10))
+            .filter(AnalyzerFilter::Mapper("synonyms.txt".into()))
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE ANALYZER autocomplete FUNCTION fn::strip_html TOKENIZERS camel \
+             FILTERS ascii,edgengram(2, 10),mapper('synonyms.txt');"
+        );
+    }
+
+    #[test]
+    fn define_event_body() {
+        let mut log = QueryBuilder::new();
+        log.from("user")
+            .where_complex(Condition::eq("id", Raw::new("$after.id")));
+        let sql = DefineEventBuilder::new("email_changed", "user")
+            .when(Condition::all([
+                Condition::raw("$event = 'UPDATE'"),
+                Condition::raw("$before.email != $after.email"),
+            ]))
+            .then_statement("CREATE log SET user = $value.id, email = $after.email")
+            .then_query(&log)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE EVENT email_changed ON TABLE user \
+             WHEN ($event = 'UPDATE' AND $before.email != $after.email) \
+             THEN { CREATE log SET user = $value.id, email = $after.email; \
+             SELECT * FROM user WHERE id = $after.id; };"
+        );
+
+        let mut script = ScriptBuilder::new();
+        script
+            .comment("note")
+            .let_raw("total", "count(SELECT * FROM post)");
+        let sql = DefineEventBuilder::new("recount", "post")
+            .then_script(&script)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE EVENT recount ON TABLE post THEN { LET $total = (count(SELECT * FROM post)); };"
+        );
+        assert!(DefineEventBuilder::new("noop", "post").build().is_err());
+    }
+
+    #[test]
+    fn define_function_from_script() {
+        let mut script = ScriptBuilder::new();
+        script
+            .comment("note")
+            .let_raw("posts", "SELECT * FROM post WHERE author = $user")
+            .returning(vec![("count", "count($posts)")]);
+        let sql = DefineFunctionBuilder::new("fn::post_count")
+            .arg("$user", "record<user>")
+            .returns("object")
+            .body_script(&script)
+            .unwrap()
+            .permissions("FULL")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE FUNCTION fn::post_count($user: record<user>) -> object { \
+             LET $posts = (SELECT * FROM post WHERE author = $user); \
+             RETURN { count: count($posts) }; } PERMISSIONS FULL;"
+        );
+        assert!(DefineFunctionBuilder::new("empty").build().is_err());
+    }
+
+    #[test]
+    fn define_param_values() {
+        let sql = DefineParamBuilder::new("$max_upload", 10_485_760).build();
+        assert_eq!(sql, "DEFINE PARAM $max_upload VALUE 10485760;");
+
+        let sql = DefineParamBuilder::new("regions", vec!["eu", "us"])
+            .permissions("NONE")
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE PARAM $regions VALUE ['eu', 'us'] PERMISSIONS NONE;"
+        );
+    }
+
+    #[test]
+    fn define_record_access() {
+        let mut signin = QueryBuilder::new();
+        signin
+            .from("user")
+            .where_complex(Condition::eq("email", Raw::new("$email")))
+            .where_simple("crypto::argon2::compare(password, $password)");
+        let sql = DefineAccessBuilder::record("account")
+            .signup(
+                "CREATE user SET email = $email, password = crypto::argon2::generate($password);",
+            )
+            .signin_query(&signin)
+            .unwrap()
+            .token_duration(Duration::from_secs(15 * 60))
+            .session_duration(Duration::from_secs(12 * 3600))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE ACCESS account ON DATABASE TYPE RECORD \
+             SIGNUP (CREATE user SET email = $email, password = crypto::argon2::generate($password)) \
+             SIGNIN (SELECT * FROM user WHERE email = $email AND crypto::argon2::compare(password, $password)) \
+             DURATION FOR TOKEN 15m, FOR SESSION 12h;"
+        );
+    }
+
+    #[test]
+    fn define_jwt_access() {
+        let sql = DefineAccessBuilder::jwt("issuer")
+            .on_namespace()
+            .key(JwtAlgorithm::Hs512, "s3cr'et")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE ACCESS issuer ON NAMESPACE TYPE JWT ALGORITHM HS512 KEY 's3cr\\'et';"
+        );
+        let sql = DefineAccessBuilder::jwt("auth0")
+            .url("https://example.com/.well-known/jwks.json")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DEFINE ACCESS auth0 ON DATABASE TYPE JWT URL 'https://example.com/.well-known/jwks.json';"
+        );
+        assert!(DefineAccessBuilder::jwt("bare").build().is_err());
+        let err = DefineAccessBuilder::jwt("mixed")
+            .url("https://example.com")
+            .signin("SELECT * FROM user")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "SIGNUP and SIGNIN require record access.");
+    }
+
+    #[test]
+    fn define_namespace_and_database() {
+        let ns = DefineNamespaceBuilder::new("tenant-42")
+            .if_not_exists()
+            .comment("Acme's tenant")
+            .build();
+        assert_eq!(
+            ns,
+            "DEFINE NAMESPACE IF NOT EXISTS `tenant-42` COMMENT 'Acme\\'s tenant';"
+        );
+        assert_eq!(
+            DefineDatabaseBuilder::new("app").build(),
+            "DEFINE DATABASE app;"
+        );
+    }
+
+    #[test]
+    fn typed_permissions() {
+        use PermissionAction::*;
+        let owner = Condition::eq("author", Raw::new("$auth.id"));
+        let sql = DefineTableBuilder::new("post")
+            .permissions(
+                Permissions::new()
+                    .allow(
+                        &[Select],
+                        Condition::any([Condition::eq("published", true), owner.clone()]),
+                    )
+                    .allow(&[Create, Update], owner)
+                    .allow(&[Delete], Permission::None),
+            )
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE TABLE post PERMISSIONS FOR select WHERE (published = true OR author = $auth.id) \
+             FOR create, update WHERE author = $auth.id FOR delete NONE;"
+        );
+        assert_eq!(
+            DefineParamBuilder::new("x", 1)
+                .permissions(Permissions::Full)
+                .build(),
+            "DEFINE PARAM $x VALUE 1 PERMISSIONS FULL;"
+        );
+        assert_eq!(Permissions::new().to_surql(), "NONE");
+    }
+
+    #[test]
+    fn remove_statements() {
+        assert_eq!(
+            RemoveBuilder::table("user").if_exists().build(),
+            "REMOVE TABLE IF EXISTS user;"
+        );
+        assert_eq!(
+            RemoveBuilder::field("settings.theme", "user").build(),
+            "REMOVE FIELD settings.theme ON TABLE user;"
+        );
+        assert_eq!(
+            RemoveBuilder::index("user_email", "user").build(),
+            "REMOVE INDEX user_email ON TABLE user;"
+        );
+        assert_eq!(
+            RemoveBuilder::function("fn::post_count").build(),
+            "REMOVE FUNCTION fn::post_count;"
+        );
+        assert_eq!(
+            RemoveBuilder::param("$max_upload").if_exists().build(),
+            "REMOVE PARAM IF EXISTS $max_upload;"
+        );
+        assert_eq!(
+            RemoveBuilder::access("account").on_namespace().build(),
+            "REMOVE ACCESS account ON NAMESPACE;"
+        );
+        assert_eq!(
+            RemoveBuilder::user("admin").on_root().build(),
+            "REMOVE USER admin ON ROOT;"
+        );
+        assert_eq!(RemoveBuilder::table("x").kind(), StatementKind::Remove);
+    }
+
+    #[test]
+    fn info_for_statements() {
+        assert_eq!(InfoBuilder::root().build(), "INFO FOR ROOT;");
+        assert_eq!(InfoBuilder::namespace().build(), "INFO FOR NS;");
+        assert_eq!(
+            InfoBuilder::database().structure().build(),
+            "INFO FOR DB STRUCTURE;"
+        );
+        assert_eq!(
+            InfoBuilder::table("user-pref").build(),
+            "INFO FOR TABLE `user-pref`;"
+        );
+        assert_eq!(
+            InfoBuilder::user("admin").on_root().build(),
+            "INFO FOR USER admin ON ROOT;"
+        );
+        assert!(StatementKind::of(&InfoBuilder::root().build()).is_read_only());
+    }
+
+    #[test]
+    fn changefeed_and_show_changes() {
+        let sql = DefineTableBuilder::new("order")
+            .changefeed(Duration::from_secs(7 * 24 * 3600))
+            .include_original()
+            .build();
+        assert_eq!(sql, "DEFINE TABLE `order` CHANGEFEED 1w INCLUDE ORIGINAL;");
+
+        let sql = ShowChangesBuilder::table("order")
+            .since_versionstamp(1024)
+            .limit(100)
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SHOW CHANGES FOR TABLE `order` SINCE 1024 LIMIT 100;");
+        let sql = ShowChangesBuilder::database()
+            .since_time(Value::datetime("2024-01-01T00:00:00Z"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SHOW CHANGES FOR DATABASE SINCE d'2024-01-01T00:00:00Z';"
+        );
+        assert!(ShowChangesBuilder::table("order").build().is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_schema() {
+        use crate::SurrealSchema;
+
+        #[allow(dead_code)]
+        #[derive(SurrealSchema)]
+        #[surreal(schemafull)]
+        struct UserProfile {
+            #[surreal(unique, assert = "string::is::email($value)")]
+            email: String,
+            nickname: Option<String>,
+            #[surreal(rename = "tags", index)]
+            labels: Vec<String>,
+            #[surreal(type = "datetime", default = "time::now()", readonly)]
+            created_at: String,
+            session_ttl: std::time::Duration,
+            #[surreal(type = "object", flexible)]
+            settings: BTreeMapAlias,
+            #[surreal(skip)]
+            cache: Vec<u8>,
+        }
+        type BTreeMapAlias = std::collections::BTreeMap<String, String>;
+
+        assert_eq!(
+            UserProfile::schema().unwrap(),
+            "DEFINE TABLE user_profile SCHEMAFULL;\n\
+             DEFINE FIELD email ON TABLE user_profile TYPE string ASSERT string::is::email($value);\n\
+             DEFINE FIELD nickname ON TABLE user_profile TYPE option<string>;\n\
+             DEFINE FIELD tags ON TABLE user_profile TYPE array<string>;\n\
+             DEFINE FIELD created_at ON TABLE user_profile TYPE datetime DEFAULT time::now() READONLY;\n\
+             DEFINE FIELD session_ttl ON TABLE user_profile TYPE duration;\n\
+             DEFINE FIELD settings ON TABLE user_profile FLEXIBLE TYPE object;\n\
+             DEFINE INDEX user_profile_email ON TABLE user_profile FIELDS email UNIQUE;\n\
+             DEFINE INDEX user_profile_tags ON TABLE user_profile FIELDS tags;"
+        );
+    }
+
+    #[test]
+    fn define_table_view() {
+        let mut view = QueryBuilder::new();
+        view.select("count()", Some("total"))
+            .select("country", None)
+            .from("user")
+            .group_by("country");
+        let sql = DefineTableBuilder::new("users_by_country")
+            .drop()
+            .schemaless()
+            .as_view(&view)
+            .unwrap()
+            .build();
+        assert_eq!(
+            sql,
+            "DEFINE TABLE users_by_country DROP SCHEMALESS AS \
+             SELECT count() AS total, country FROM user GROUP BY country;"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Typed expressions that render to SurrealQL: function calls and casts.
+
+use crate::{Condition, Operator, Path, Value};
+
+/// A call to a SurrealQL function such as `array::len` or `string::lowercase`.
+///
+/// Calls convert into [`Value`] (for SET values and arguments of other
+/// calls) and into [`Condition`] (for functions returning a bool), and
+/// render with `to_surql()` for select items.
+///
+/// ```
+/// use surrealex::{Condition, func};
+///
+/// let len = func("array::len").field("tags");
+/// assert_eq!(len.to_surql(), "array::len(tags)");
+/// assert_eq!(
+///     Condition::from(func("string::is::email").field("email")),
+///     Condition::raw("string::is::email(email)")
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func {
+    name: String,
+    args: Vec<String>,
+}
+
+/// Starts a call to the function `name`, e.g. `math::max` or `time::now`.
+/// The name is rendered verbatim.
+pub fn func(name: &str) -> Func {
+    Func {
+        name: name.to_string(),
+        args: Vec::new(),
+    }
+}
+
+impl Func {
+    /// Appends a literal argument; pass another `Func` or a `param()` for
+    /// nested calls and parameters.
+    pub fn arg(mut self, value: impl Into<Value>) -> Self {
+        self.args.push(value.into().to_surql());
+        self
+    }
+
+    /// Appends a field (or `Path`) argument, escaped as an identifier.
+    pub fn field(mut self, field: impl Into<Path>) -> Self {
+        self.args.push(field.into().to_surql());
+        self
+    }
+
+    /// Renders the call as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        format!("{}({})", self.name, self.args.join(", "))
+    }
+}
+
+/// `type::thing(table, id)`, a record id built from a table name and an id
+/// value, e.g. `type_thing("user", param("id"))`.
+pub fn type_thing(table: &str, id: impl Into<Value>) -> Func {
+    func("type::thing").arg(table).arg(id)
+}
+
+/// `type::string(value)`
+pub fn type_string(value: impl Into<Value>) -> Func {
+    func("type::string").arg(value)
+}
+
+/// `type::number(value)`
+pub fn type_number(value: impl Into<Value>) -> Func {
+    func("type::number").arg(value)
+}
+
+/// A cast such as `<int> $x` or `<datetime> created`.
+///
+/// ```
+/// use surrealex::{Condition, cast, cast_field, param};
+///
+/// assert_eq!(cast("int", param("x")).to_surql(), "<int> $x");
+/// assert_eq!(
+///     cast_field("datetime", "created").gt(param("since")),
+///     Condition::raw("<datetime> created > $since")
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    ty: String,
+    expr: String,
+}
+
+/// Casts a value, parameter or nested expression to `ty`, e.g. `int`,
+/// `datetime` or `array<string>`.
+pub fn cast(ty: &str, value: impl Into<Value>) -> Cast {
+    Cast {
+        ty: ty.to_string(),
+        expr: value.into().to_surql(),
+    }
+}
+
+/// Casts a field to `ty`; the field is escaped as an identifier.
+pub fn cast_field(ty: &str, field: impl Into<Path>) -> Cast {
+    Cast {
+        ty: ty.to_string(),
+        expr: field.into().to_surql(),
+    }
+}
+
+impl Cast {
+    /// Renders the cast as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        format!("<{}> {}", self.ty, self.expr)
+    }
+}
+
+/// Comparisons, `Display` and the `Value`/`Condition` conversions shared by
+/// the expression types.
+macro_rules! expression {
+    ($($ty:ty),*) => {$(
+        impl $ty {
+            fn compare(&self, op: Operator, value: impl Into<Value>) -> Condition {
+                Condition::Raw(format!(
+                    "{} {} {}",
+                    self.to_surql(),
+                    op.as_surql(),
+                    value.into().to_surql()
+                ))
+            }
+
+            /// `expr = value`
+            pub fn eq(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Eq, value)
+            }
+
+            /// `expr != value`
+            pub fn ne(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Ne, value)
+            }
+
+            /// `expr > value`
+            pub fn gt(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Gt, value)
+            }
+
+            /// `expr >= value`
+            pub fn gte(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Gte, value)
+            }
+
+            /// `expr < value`
+            pub fn lt(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Lt, value)
+            }
+
+            /// `expr <= value`
+            pub fn lte(&self, value: impl Into<Value>) -> Condition {
+                self.compare(Operator::Lte, value)
+            }
+        }
+
+        impl std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.to_surql())
+            }
+        }
+
+        impl From<$ty> for Value {
+            fn from(expr: $ty) -> Self {
+                Value::Raw(expr.to_surql())
+            }
+        }
+
+        impl From<$ty> for Condition {
+            fn from(expr: $ty) -> Self {
+                Condition::Raw(expr.to_surql())
+            }
+        }
+    )*};
+}
+
+expression!(Func, Cast);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryBuilder;
+
+    #[test]
+    fn function_calls_in_queries() {
+        let sql = QueryBuilder::new()
+            .select("id", None)
+            .select(
+                &func("string::lowercase").field("display-name").to_surql(),
+                Some("name"),
+            )
+            .from("user")
+            .where_complex(func("array::len").field("tags").gt(2))
+            .where_complex(Condition::gt(
+                "created",
+                func("time::floor")
+                    .arg(func("time::now"))
+                    .arg(std::time::Duration::from_secs(86_400)),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, string::lowercase(`display-name`) AS name FROM user \
+             WHERE array::len(tags) > 2 AND created > time::floor(time::now(), 1d)"
+        );
+        assert_eq!(
+            func("math::max").arg(vec![1, 5, 3]).to_string(),
+            "math::max([1, 5, 3])"
+        );
+    }
+
+    #[test]
+    fn casts_and_conversions() {
+        let sql = QueryBuilder::new()
+            .select(&type_string(crate::param("n")).to_surql(), Some("label"))
+            .from("reading")
+            .where_complex(cast_field("float", "value").gte(cast("float", "1.5")))
+            .where_complex(Condition::eq(
+                "owner",
+                type_thing("user", crate::param("id")),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT type::string($n) AS label FROM reading \
+             WHERE <float> value >= <float> '1.5' AND owner = type::thing('user', $id)"
+        );
+        assert_eq!(
+            func("math::sum")
+                .arg(type_number(crate::param("a")))
+                .to_surql(),
+            "math::sum(type::number($a))"
+        );
+    }
+}
@@ -0,0 +1,532 @@
+//! Graph traversal chains such as `->friends->person<-authored<-post`.
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::{
+    BuildError, Condition, Direction, Path, QueryBuilder, Value, dedup_conditions, escape_ident,
+    render_condition, render_destructure,
+};
+
+/// What a traversal selects from the records it reaches.
+#[derive(Debug, Clone, PartialEq)]
+enum Projection {
+    /// `.*`
+    All,
+    /// `.field`
+    Field(Path),
+    /// `.{a, b}`
+    Destructure(Vec<String>),
+}
+
+/// Repetition of a traversal, `@.{min..max+modes}(...)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Recursion {
+    /// Inclusive lower bound on the depth.
+    min: Option<u32>,
+    /// Inclusive upper bound on the depth.
+    max: Option<u32>,
+    mode: Option<RecursionMode>,
+    /// `+inclusive`: include the starting record in the collected output.
+    inclusive: bool,
+}
+
+impl Recursion {
+    fn to_surql(&self) -> String {
+        let mut out = match (self.min, self.max) {
+            (Some(min), Some(max)) if min == max => min.to_string(),
+            (min, max) => format!(
+                "{}..{}",
+                min.map(|n| n.to_string()).unwrap_or_default(),
+                max.map(|n| n.to_string()).unwrap_or_default()
+            ),
+        };
+        match &self.mode {
+            Some(RecursionMode::Collect) => out.push_str("+collect"),
+            Some(RecursionMode::Path) => out.push_str("+path"),
+            Some(RecursionMode::Shortest(target)) => out.push_str(&format!("+shortest={}", target)),
+            None => {}
+        }
+        if self.inclusive {
+            out.push_str("+inclusive");
+        }
+        format!("@.{{{}}}", out)
+    }
+}
+
+/// What a recursive traversal returns.
+#[derive(Debug, Clone, PartialEq)]
+enum RecursionMode {
+    /// `+collect`: every record reached, rather than only the last level.
+    Collect,
+    /// `+path`: every path walked, as arrays of records.
+    Path,
+    /// `+shortest=target`: the shortest path to the target record.
+    Shortest(String),
+}
+
+/// The deepest recursion SurrealDB accepts.
+const MAX_RECURSION_DEPTH: u32 = 256;
+
+/// One hop of a traversal.
+#[derive(Debug, Clone)]
+struct Step {
+    direction: Direction,
+    table: String,
+    /// Filters applied while expanding the hop, joined by AND.
+    conditions: Vec<Condition>,
+}
+
+/// A graph traversal of any depth, built hop by hop.
+///
+/// ```
+/// use surrealex::{QueryBuilder, Traversal};
+///
+/// let liked = Traversal::new()
+///     .out("friends")
+///     .out("person")
+///     .out("likes")
+///     .out("post")
+///     .field("title")
+///     .alias("liked_titles");
+/// let sql = QueryBuilder::new()
+///     .select("name", None)
+///     .traverse(&liked)
+///     .unwrap()
+///     .from("user")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     sql,
+///     "SELECT name, ->friends->person->likes->post.title AS liked_titles FROM user"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Traversal {
+    steps: Vec<Step>,
+    projection: Option<Projection>,
+    alias: Option<String>,
+    recursion: Option<Recursion>,
+    /// Set when `filter` was called before any hop; reported by `validate`.
+    orphan_filter: bool,
+}
+
+impl Traversal {
+    /// Starts an empty traversal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an outgoing hop, `->table`.
+    pub fn out(self, table: &str) -> Self {
+        self.step(Direction::Out, table)
+    }
+
+    /// Adds an incoming hop, `<-table`.
+    pub fn r#in(self, table: &str) -> Self {
+        self.step(Direction::In, table)
+    }
+
+    /// Adds a hop in either direction, `<->table`.
+    pub fn both(self, table: &str) -> Self {
+        self.step(Direction::Both, table)
+    }
+
+    /// Adds a hop in the given direction.
+    pub fn step(mut self, direction: Direction, table: &str) -> Self {
+        self.steps.push(Step {
+            direction,
+            table: table.to_string(),
+            conditions: Vec::new(),
+        });
+        self
+    }
+
+    /// Filters the most recent hop while it is expanded, rendering
+    /// `->(likes WHERE since > d'2024-01-01')`. Calling it again on the same
+    /// hop ANDs the conditions. A filter with no hop before it makes
+    /// `traverse` return Err.
+    pub fn filter(mut self, condition: impl Into<Condition>) -> Self {
+        match self.steps.last_mut() {
+            Some(step) => step.conditions.push(condition.into()),
+            None => self.orphan_filter = true,
+        }
+        self
+    }
+
+    /// Repeats the hops from the current record (`@`) for a number of levels:
+    /// `3..=3` renders `@.{3}`, `1..=3` renders `@.{1..3}` and `..` leaves
+    /// the depth open (`@.{..}`). Useful for org charts and dependency trees.
+    pub fn recursive(mut self, depth: impl RangeBounds<u32>) -> Self {
+        let min = match depth.start_bound() {
+            Bound::Included(n) => Some(*n),
+            Bound::Excluded(n) => Some(n + 1),
+            Bound::Unbounded => None,
+        };
+        let max = match depth.end_bound() {
+            Bound::Included(n) => Some(*n),
+            Bound::Excluded(n) => Some(n.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+        let recursion = self.recursion.get_or_insert_with(Recursion::default);
+        recursion.min = min;
+        recursion.max = max;
+        self
+    }
+
+    /// Collects every record reached at any depth (`+collect`), instead of
+    /// only those at the final level. Implies an open depth if
+    /// [`Traversal::recursive`] was not called.
+    pub fn collect(self) -> Self {
+        self.mode(RecursionMode::Collect)
+    }
+
+    /// Returns every path walked (`+path`), each as an array of records.
+    pub fn path(self) -> Self {
+        self.mode(RecursionMode::Path)
+    }
+
+    /// Returns the shortest path to `target` (`+shortest=person:b`), usually
+    /// a `RecordId` or a parameter.
+    ///
+    /// ```
+    /// use surrealex::{RecordId, Traversal};
+    ///
+    /// let path = Traversal::new()
+    ///     .out("knows")
+    ///     .out("person")
+    ///     .shortest(RecordId::new("person", "star"));
+    /// assert_eq!(
+    ///     path.to_surql(),
+    ///     "@.{..+shortest=person:star}(->knows->person)"
+    /// );
+    /// ```
+    pub fn shortest(self, target: impl Into<Value>) -> Self {
+        self.mode(RecursionMode::Shortest(target.into().to_surql()))
+    }
+
+    fn mode(mut self, mode: RecursionMode) -> Self {
+        self.recursion.get_or_insert_with(Recursion::default).mode = Some(mode);
+        self
+    }
+
+    /// Includes the starting record in the collected output (`+inclusive`).
+    pub fn inclusive(mut self) -> Self {
+        self.recursion
+            .get_or_insert_with(Recursion::default)
+            .inclusive = true;
+        self
+    }
+
+    /// Ends the traversal with `.*`, selecting whole records.
+    pub fn all_fields(mut self) -> Self {
+        self.projection = Some(Projection::All);
+        self
+    }
+
+    /// Ends the traversal with a single field, e.g. `.name`.
+    pub fn field(mut self, field: impl Into<Path>) -> Self {
+        self.projection = Some(Projection::Field(field.into()));
+        self
+    }
+
+    /// Ends the traversal with a destructuring of the given fields, e.g.
+    /// `->purchased->product.{name, price}`. Each field is escaped.
+    pub fn destructure(mut self, fields: &[&str]) -> Self {
+        self.projection = Some(Projection::Destructure(
+            fields.iter().map(|f| f.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Names the traversal in a select list (`... AS alias`).
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    /// Checks that the traversal has hops and a recursion depth SurrealDB
+    /// accepts.
+    fn validate(&self) -> Result<(), BuildError> {
+        if self.steps.is_empty() {
+            return Err(BuildError::new("A traversal needs at least one hop.")
+                .with_suggestion("call .out(table) or .r#in(table) on the traversal"));
+        }
+        if self.orphan_filter {
+            return Err(BuildError::new("A filter needs a hop to apply to.")
+                .with_suggestion("call .filter() after the .out(table) it filters"));
+        }
+        if let Some(recursion) = &self.recursion {
+            let min = recursion.min.unwrap_or(1);
+            let max = recursion.max.unwrap_or(MAX_RECURSION_DEPTH);
+            if min == 0 || min > max || max > MAX_RECURSION_DEPTH {
+                return Err(
+                    BuildError::new("Recursion depth must be between 1 and 256.")
+                        .with_suggestion("pass a range such as 1..=3 to .recursive()"),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the traversal as SurrealQL, without the alias.
+    pub fn to_surql(&self) -> String {
+        let mut out = String::new();
+        if self.recursion.is_some() {
+            out.push('(');
+        }
+        for step in &self.steps {
+            out.push_str(step.direction.arrow());
+            if step.conditions.is_empty() {
+                out.push_str(&escape_ident(&step.table));
+            } else {
+                let rendered: Vec<String> = dedup_conditions(&step.conditions)
+                    .into_iter()
+                    .map(render_condition)
+                    .collect();
+                out.push_str(&format!(
+                    "({} WHERE {})",
+                    escape_ident(&step.table),
+                    rendered.join(" AND ")
+                ));
+            }
+        }
+        if let Some(recursion) = &self.recursion {
+            out = format!("{}{})", recursion.to_surql(), out);
+        }
+        match &self.projection {
+            Some(Projection::All) => out.push_str(".*"),
+            Some(Projection::Field(path)) => {
+                out.push('.');
+                out.push_str(&path.to_surql());
+            }
+            Some(Projection::Destructure(fields)) => {
+                out.push_str(&format!(".{{{}}}", render_destructure(fields)))
+            }
+            None => {}
+        }
+        out
+    }
+}
+
+impl QueryBuilder {
+    /// Adds a traversal to the SELECT list, aliased if the traversal has an
+    /// alias. Returns Err if the traversal has no hops, a filter before its
+    /// first hop or an invalid depth.
+    pub fn traverse(&mut self, traversal: &Traversal) -> Result<&mut Self, BuildError> {
+        traversal.validate()?;
+        let mut item = traversal.to_surql();
+        if let Some(alias) = &traversal.alias {
+            item.push_str(" AS ");
+            item.push_str(&escape_ident(alias));
+        }
+        Ok(self.graph_expand(&item))
+    }
+
+    /// Fetches the records a traversal reaches, e.g. `FETCH ->likes->post`.
+    /// The alias and any projection are ignored. Returns Err on the same
+    /// traversals as `traverse`.
+    pub fn fetch_traversal(&mut self, traversal: &Traversal) -> Result<&mut Self, BuildError> {
+        traversal.validate()?;
+        let mut target = traversal.clone();
+        target.projection = None;
+        Ok(self.fetch(&target.to_surql()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traversal_chains() {
+        let chain = Traversal::new()
+            .r#in("authored")
+            .r#in("user")
+            .out("follows")
+            .out("user")
+            .all_fields();
+        assert_eq!(chain.to_surql(), "<-authored<-user->follows->user.*");
+
+        let sql = QueryBuilder::new()
+            .from("post")
+            .traverse(&chain.alias("followers"))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT *, <-authored<-user->follows->user.* AS followers FROM post"
+        );
+        assert_eq!(
+            QueryBuilder::new().traverse(&Traversal::new()).unwrap_err(),
+            "A traversal needs at least one hop."
+        );
+    }
+
+    #[test]
+    fn filtered_hops() {
+        let recent = Traversal::new()
+            .out("likes")
+            .filter(Condition::gt("since", crate::Value::datetime("2024-01-01")))
+            .filter(Condition::eq("public", true))
+            .out("post")
+            .field("title");
+        assert_eq!(
+            recent.to_surql(),
+            "->(likes WHERE since > d'2024-01-01' AND public = true)->post.title"
+        );
+
+        let err = QueryBuilder::new()
+            .traverse(
+                &Traversal::new()
+                    .filter(Condition::eq("public", true))
+                    .out("likes"),
+            )
+            .unwrap_err();
+        assert_eq!(err, "A filter needs a hop to apply to.");
+    }
+
+    #[test]
+    fn undirected_hops() {
+        let mutual = Traversal::new()
+            .both("friends_with")
+            .both("person")
+            .alias("friends");
+        let sql = QueryBuilder::new()
+            .select("name", None)
+            .traverse(&mutual)
+            .unwrap()
+            .from("person:tobie")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name, <->friends_with<->person AS friends FROM person:tobie"
+        );
+    }
+
+    #[test]
+    fn recursive_traversals() {
+        let reports = Traversal::new()
+            .r#in("reports_to")
+            .r#in("employee")
+            .recursive(1..=3)
+            .collect()
+            .alias("reports");
+        let sql = QueryBuilder::new()
+            .select("name", None)
+            .traverse(&reports)
+            .unwrap()
+            .from("employee:ceo")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name, @.{1..3+collect}(<-reports_to<-employee) AS reports FROM employee:ceo"
+        );
+        assert_eq!(
+            Traversal::new().out("depends_on").recursive(2..).to_surql(),
+            "@.{2..}(->depends_on)"
+        );
+        assert_eq!(
+            Traversal::new()
+                .out("knows")
+                .out("person")
+                .recursive(3..4)
+                .field("name")
+                .to_surql(),
+            "@.{3}(->knows->person).name"
+        );
+        let err = QueryBuilder::new()
+            .traverse(&Traversal::new().out("knows").recursive(0..=300))
+            .unwrap_err();
+        assert_eq!(err, "Recursion depth must be between 1 and 256.");
+    }
+
+    #[test]
+    fn destructured_end() {
+        let items = Traversal::new()
+            .out("purchased")
+            .out("product")
+            .destructure(&["name", "price", "in-stock"])
+            .alias("items");
+        let sql = QueryBuilder::new()
+            .select("id", None)
+            .traverse(&items)
+            .unwrap()
+            .from("customer")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, ->purchased->product.{name, price, `in-stock`} AS items FROM customer"
+        );
+    }
+
+    #[test]
+    fn path_modes() {
+        let paths = Traversal::new()
+            .out("depends_on")
+            .out("package")
+            .recursive(..=4)
+            .path()
+            .inclusive();
+        assert_eq!(
+            paths.to_surql(),
+            "@.{..4+path+inclusive}(->depends_on->package)"
+        );
+        let sql = QueryBuilder::new()
+            .select("id", None)
+            .traverse(&paths.shortest(crate::param("target")).alias("route"))
+            .unwrap()
+            .from("package:app")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, @.{..4+shortest=$target+inclusive}(->depends_on->package) AS route \
+             FROM package:app"
+        );
+    }
+
+    #[test]
+    fn fetch_traversals_and_aliases() {
+        let liked = Traversal::new().out("likes").out("post").alias("liked");
+        let sql = QueryBuilder::new()
+            .from("user")
+            .traverse(&liked)
+            .unwrap()
+            .fetch_alias("liked")
+            .fetch_traversal(&liked)
+            .unwrap()
+            .fetch_path("profile.avatar")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT *, ->likes->post AS liked FROM user FETCH liked, ->likes->post, profile.avatar"
+        );
+
+        let err = QueryBuilder::new()
+            .select("name", Some("n"))
+            .from("user")
+            .fetch_alias("friends")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "FETCH references an alias that is not in the SELECT list."
+        );
+
+        let err = QueryBuilder::new()
+            .from("user")
+            .fetch_traversal(&Traversal::new().out("a").recursive(0..=0))
+            .unwrap_err();
+        assert_eq!(err, "Recursion depth must be between 1 and 256.");
+        let err = QueryBuilder::new()
+            .from("user")
+            .fetch_traversal(&Traversal::new())
+            .unwrap_err();
+        assert_eq!(err, "A traversal needs at least one hop.");
+    }
+}
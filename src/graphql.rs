@@ -0,0 +1,180 @@
+//! Translating async-graphql resolvers into SurrealQL queries.
+
+use async_graphql::{
+    Context, Enum, InputObject, InputValueError, InputValueResult, Scalar, ScalarType,
+    SelectionField,
+};
+
+use crate::{Condition, Path, QueryBuilder, Value};
+
+/// Builds a query against `table` projecting the fields requested by the
+/// resolver's selection set.
+pub fn query_for(ctx: &Context<'_>, table: &str) -> QueryBuilder {
+    let mut qb = QueryBuilder::new();
+    qb.from(table).select_graphql(ctx.field());
+    qb
+}
+
+impl QueryBuilder {
+    /// Selects the fields requested below a GraphQL field. Fields with their
+    /// own selection set are treated as record links and fetched; introspection
+    /// fields such as `__typename` are skipped.
+    pub fn select_graphql(&mut self, field: SelectionField<'_>) -> &mut Self {
+        let mut seen: Vec<&str> = Vec::new();
+        for child in field.selection_set() {
+            let name = child.name();
+            if name.starts_with("__") || seen.contains(&name) {
+                continue;
+            }
+            seen.push(name);
+            self.select_path(&Path::new(name), None);
+            if child.selection_set().next().is_some() {
+                self.fetch(&Path::new(name).to_surql());
+            }
+        }
+        self
+    }
+}
+
+/// The comparison a [`FieldFilter`] applies.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    /// `=`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `>`
+    Gt,
+    /// `>=`
+    Gte,
+    /// `<`
+    Lt,
+    /// `<=`
+    Lte,
+    /// `IN [..]`; a scalar operand is treated as a one-element list.
+    In,
+}
+
+/// Any GraphQL input value, used as the operand of a [`FieldFilter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterValue(pub Value);
+
+#[Scalar(name = "FilterValue")]
+impl ScalarType for FilterValue {
+    fn parse(value: async_graphql::Value) -> InputValueResult<Self> {
+        match value {
+            async_graphql::Value::Binary(_) => Err(InputValueError::custom(
+                "binary values are not supported in filters",
+            )),
+            v => Ok(Self(v.into())),
+        }
+    }
+
+    fn to_value(&self) -> async_graphql::Value {
+        async_graphql::Value::String(self.0.to_surql())
+    }
+}
+
+/// A single `field op value` filter argument.
+#[derive(InputObject, Debug, Clone)]
+pub struct FieldFilter {
+    /// The field to compare; dots separate nested fields.
+    pub field: String,
+    /// The comparison operator.
+    pub op: FilterOp,
+    /// The operand.
+    pub value: FilterValue,
+}
+
+impl From<FieldFilter> for Condition {
+    fn from(filter: FieldFilter) -> Self {
+        let field = filter.field.as_str();
+        let value = filter.value.0;
+        match filter.op {
+            FilterOp::Eq => Condition::eq(field, value),
+            FilterOp::Ne => Condition::ne(field, value),
+            FilterOp::Gt => Condition::gt(field, value),
+            FilterOp::Gte => Condition::gte(field, value),
+            FilterOp::Lt => Condition::lt(field, value),
+            FilterOp::Lte => Condition::lte(field, value),
+            FilterOp::In => match value {
+                Value::Array(values) => Condition::is_in(field, values),
+                v => Condition::is_in(field, vec![v]),
+            },
+        }
+    }
+}
+
+impl From<async_graphql::Value> for Value {
+    /// Object fields keep the order they were written in the request.
+    fn from(value: async_graphql::Value) -> Self {
+        use async_graphql::Value as Gql;
+        match value {
+            Gql::Null => Value::Null,
+            Gql::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Gql::String(s) => Value::String(s),
+            Gql::Boolean(b) => Value::Bool(b),
+            Gql::Binary(bytes) => {
+                Value::Array(bytes.iter().map(|b| Value::Int(i64::from(*b))).collect())
+            }
+            Gql::Enum(name) => Value::String(name.to_string()),
+            Gql::List(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            Gql::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_become_conditions() {
+        let filters = vec![
+            FieldFilter {
+                field: "profile.age".into(),
+                op: FilterOp::Gte,
+                value: FilterValue(Value::Int(18)),
+            },
+            FieldFilter {
+                field: "status".into(),
+                op: FilterOp::In,
+                value: FilterValue(Value::String("active".into())),
+            },
+        ];
+        let sql = QueryBuilder::new()
+            .from("user")
+            .where_complex(Condition::all(filters.into_iter().map(Condition::from)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM user WHERE (profile.age >= 18 AND status IN ['active'])"
+        );
+    }
+
+    #[test]
+    fn graphql_values_convert() {
+        let value = async_graphql::Value::from_json(serde_json::json!({
+            "name": "x",
+            "tags": [1, 2.5, null],
+        }))
+        .unwrap();
+        assert_eq!(
+            Value::from(value).to_surql(),
+            "{ name: 'x', tags: [1, 2.5, NULL] }"
+        );
+        assert!(
+            <FilterValue as ScalarType>::parse(async_graphql::Value::Binary(vec![1u8].into()))
+                .is_err()
+        );
+    }
+}
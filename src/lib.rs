@@ -1,31 +1,503 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+use std::time::Duration;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+mod batch;
+mod control;
+mod define;
+mod expr;
+mod graph;
+#[cfg(feature = "async-graphql")]
+pub mod graphql;
+mod live;
+pub mod migrations;
+pub mod monitor;
+pub mod presets;
+#[cfg(feature = "serde")]
+mod serialize;
+
+#[cfg(feature = "serde")]
+pub use serialize::{SerializeError, to_surql_value};
+
+#[cfg(feature = "derive")]
+pub use surrealex_derive::SurrealSchema;
+
+// Lets the derive macro's `::surrealex::` paths resolve inside this crate.
+#[cfg(feature = "derive")]
+extern crate self as surrealex;
+
+pub use batch::{BatchBuilder, BatchEntry, IndexedBatch};
+pub use control::{ForLoopBuilder, ReturnBuilder, throw};
+pub use define::{
+    AnalyzerFilter, DefineAccessBuilder, DefineAnalyzerBuilder, DefineDatabaseBuilder,
+    DefineEventBuilder, DefineFieldBuilder, DefineFunctionBuilder, DefineIndexBuilder,
+    DefineNamespaceBuilder, DefineParamBuilder, DefineTableBuilder, IndexType, InfoBuilder,
+    JwtAlgorithm, Language, Permission, PermissionAction, Permissions, RemoveBuilder,
+    ShowChangesBuilder, SurrealSchema, TableType, Tokenizer,
+};
+pub use expr::{Cast, Func, cast, cast_field, func, type_number, type_string, type_thing};
+pub use graph::Traversal;
+pub use live::{LiveQueryBuilder, kill};
+
 /// Represents a logical condition or a group of conditions for a WHERE clause.
 /// This enum allows for building a tree of logical operations.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Condition {
-    /// A simple, raw condition string (e.g., "price > 50").
+    /// A simple, raw condition string (e.g., "price > 50"). Rejected by
+    /// `QueryBuilder::strict()`; prefer `Binary`, or `Raw` for trusted SQL.
     Simple(String),
+    /// `field op value`, with the value rendered as an escaped literal.
+    Binary(Path, Operator, Value),
+    /// Verbatim SurrealQL that the caller vouches for. Rendered like
+    /// `Simple`, but allowed in strict mode.
+    Raw(String),
     /// A list of conditions that will be joined by 'AND'.
     And(Vec<Condition>),
     /// A list of conditions that will be joined by 'OR'.
     Or(Vec<Condition>),
+    /// The negation of a condition, rendered as `!(...)`.
+    Not(Box<Condition>),
+    /// `field IN [values]`, with each value rendered as an escaped literal.
+    In(Path, Vec<Value>),
+}
+
+impl Condition {
+    /// `field op value`, the general form of the typed comparisons below.
+    pub fn binary(field: impl Into<Path>, op: Operator, value: impl Into<Value>) -> Self {
+        Condition::Binary(field.into(), op, value.into())
+    }
+
+    /// Verbatim SurrealQL for conditions the typed API cannot express. The
+    /// text is not escaped, so it must never contain untrusted input.
+    pub fn raw(sql: impl Into<String>) -> Self {
+        Condition::Raw(sql.into())
+    }
+
+    /// `field = value`, with the value rendered as an escaped literal. Dotted
+    /// field names are split into path segments.
+    pub fn eq(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().eq(value)
+    }
+
+    /// `field != value`
+    pub fn ne(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().ne(value)
+    }
+
+    /// `field > value`
+    pub fn gt(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().gt(value)
+    }
+
+    /// `field >= value`
+    pub fn gte(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().gte(value)
+    }
+
+    /// `field < value`
+    pub fn lt(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().lt(value)
+    }
+
+    /// `field <= value`
+    pub fn lte(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().lte(value)
+    }
+
+    /// `field ~ value`, a fuzzy match.
+    pub fn fuzzy(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().compare(Operator::Fuzzy, value)
+    }
+
+    /// `field !~ value`, the negated fuzzy match.
+    pub fn not_fuzzy(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().compare(Operator::NotFuzzy, value)
+    }
+
+    /// `field ?~ value`, true if any element of the array field fuzzy-matches.
+    pub fn any_fuzzy(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().compare(Operator::AnyFuzzy, value)
+    }
+
+    /// `field *~ value`, true if every element of the array field fuzzy-matches.
+    pub fn all_fuzzy(field: impl Into<Path>, value: impl Into<Value>) -> Self {
+        field.into().compare(Operator::AllFuzzy, value)
+    }
+
+    /// `field @ref@ 'query'`, a full-text search match. The reference number
+    /// ties the predicate to `search::score(ref)` and `search::highlight(...)`
+    /// projections, see [`QueryBuilder::select_search_score`].
+    pub fn matches(field: impl Into<Path>, reference: u32, query: &str) -> Self {
+        field.into().compare(Operator::Matches(reference), query)
+    }
+
+    /// `field <|k|> [vector]`, a k-nearest-neighbour vector search. `param`
+    /// selects the `<|k,EF|>` (HNSW search size) or `<|k,DIST|>` (distance
+    /// metric) forms.
+    pub fn knn(field: impl Into<Path>, k: u32, param: Option<KnnParam>, vector: &[f64]) -> Self {
+        field
+            .into()
+            .compare(Operator::Knn(k, param), vector.to_vec())
+    }
+
+    /// `field INSIDE geometry`
+    pub fn inside(field: impl Into<Path>, geometry: Geometry) -> Self {
+        field.into().compare(Operator::Inside, geometry)
+    }
+
+    /// `field OUTSIDE geometry`
+    pub fn outside(field: impl Into<Path>, geometry: Geometry) -> Self {
+        field.into().compare(Operator::Outside, geometry)
+    }
+
+    /// `field INTERSECTS geometry`
+    pub fn intersects(field: impl Into<Path>, geometry: Geometry) -> Self {
+        field.into().compare(Operator::Intersects, geometry)
+    }
+
+    /// `geo::distance(field, point) < meters`, matching records within the
+    /// given distance (in metres) of a point.
+    pub fn within_distance(
+        field: impl Into<Path>,
+        point: (f64, f64),
+        meters: impl Into<Value>,
+    ) -> Self {
+        Condition::Raw(format!(
+            "geo::distance({}, {}) < {}",
+            field.into(),
+            Geometry::Point(point.0, point.1).to_surql(),
+            meters.into().to_surql()
+        ))
+    }
+
+    /// `field IS NONE`, matching records where the field is absent.
+    pub fn is_none(field: impl Into<Path>) -> Self {
+        field.into().is_none()
+    }
+
+    /// `field IS NOT NONE`, matching records where the field is present.
+    pub fn is_not_none(field: impl Into<Path>) -> Self {
+        field.into().is_not_none()
+    }
+
+    /// `field = NULL`, matching records where the field is explicitly null.
+    pub fn is_null(field: impl Into<Path>) -> Self {
+        field.into().is_null()
+    }
+
+    /// `field != NULL`
+    pub fn is_not_null(field: impl Into<Path>) -> Self {
+        field.into().is_not_null()
+    }
+
+    /// `field IN (subquery)`. The subquery should select a bare list, e.g.
+    /// with `.select("VALUE id", None)`. Returns Err if it cannot be built.
+    pub fn in_subquery(field: impl Into<Path>, qb: &QueryBuilder) -> Result<Self, BuildError> {
+        Ok(Condition::Raw(format!(
+            "{} IN ({})",
+            field.into(),
+            qb.build()?
+        )))
+    }
+
+    /// `array::len((subquery)) > 0`, true if the subquery returns any rows.
+    /// Negate it for a "not exists" check. Returns Err if it cannot be built.
+    pub fn exists(qb: &QueryBuilder) -> Result<Self, BuildError> {
+        Ok(Condition::Raw(format!("array::len(({})) > 0", qb.build()?)))
+    }
+
+    /// `field IN [values]`
+    pub fn is_in<V: Into<Value>>(
+        field: impl Into<Path>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Condition::In(field.into(), values.into_iter().map(Into::into).collect())
+    }
+
+    /// Collects conditions into an `And` group. A single condition is returned
+    /// as is, and an empty group renders as `true`.
+    pub fn all(conditions: impl IntoIterator<Item = Condition>) -> Self {
+        let mut conditions: Vec<Condition> = conditions.into_iter().collect();
+        if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::And(conditions)
+        }
+    }
+
+    /// Collects conditions into an `Or` group. A single condition is returned
+    /// as is, and an empty group renders as `false`.
+    pub fn any(conditions: impl IntoIterator<Item = Condition>) -> Self {
+        let mut conditions: Vec<Condition> = conditions.into_iter().collect();
+        if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::Or(conditions)
+        }
+    }
+
+    /// Negates the condition. Negating a `Not` unwraps it instead of nesting.
+    pub fn negate(self) -> Self {
+        match self {
+            Condition::Not(inner) => *inner,
+            other => Condition::Not(Box::new(other)),
+        }
+    }
+
+    /// Returns the names (without the leading `$`) of every parameter referenced
+    /// in this condition tree. Parameters inside quoted strings and SurrealDB's
+    /// reserved parameters (`$this`, `$parent`, `$auth`, ...) are ignored, so the
+    /// result is exactly what a caller has to bind before executing the query.
+    pub fn parameters(&self) -> BTreeSet<String> {
+        let mut out = BTreeSet::new();
+        self.collect_parameters(&mut out);
+        out
+    }
+
+    /// Replaces literal operands with generated parameters, recording their
+    /// values in `bindings`.
+    fn parameterize(&mut self, next: &mut usize, bindings: &mut BTreeMap<String, Value>) {
+        let mut bind = |value: Value| {
+            let name = loop {
+                let name = format!("p{}", next);
+                *next += 1;
+                if !bindings.contains_key(&name) {
+                    break name;
+                }
+            };
+            bindings.insert(name.clone(), value);
+            Value::Param(name)
+        };
+        match self {
+            Condition::Binary(_, _, value) if !matches!(value, Value::Param(_) | Value::Raw(_)) => {
+                *value = bind(std::mem::replace(value, Value::None));
+            }
+            Condition::In(..) => {
+                if let Condition::In(field, values) =
+                    std::mem::replace(self, Condition::And(Vec::new()))
+                {
+                    *self = Condition::Binary(field, Operator::In, bind(Value::Array(values)));
+                }
+            }
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                for c in conditions {
+                    c.parameterize(next, bindings);
+                }
+            }
+            Condition::Not(inner) => inner.parameterize(next, bindings),
+            _ => {}
+        }
+    }
+
+    fn contains_simple(&self) -> bool {
+        match self {
+            Condition::Simple(_) => true,
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                conditions.iter().any(Condition::contains_simple)
+            }
+            Condition::Not(inner) => inner.contains_simple(),
+            Condition::Binary(..) | Condition::Raw(_) | Condition::In(..) => false,
+        }
+    }
+
+    fn collect_parameters(&self, out: &mut BTreeSet<String>) {
+        match self {
+            Condition::Simple(s) | Condition::Raw(s) => scan_parameters(s, out),
+            Condition::Binary(..) | Condition::In(..) => {
+                scan_parameters(&render_condition(self), out)
+            }
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                for c in conditions {
+                    c.collect_parameters(out);
+                }
+            }
+            Condition::Not(inner) => inner.collect_parameters(out),
+        }
+    }
+}
+
+/// Parameters that SurrealDB provides itself and which therefore never need
+/// to be bound by the caller.
+const RESERVED_PARAMS: &[&str] = &[
+    "access", "after", "auth", "before", "event", "input", "parent", "session", "this", "token",
+    "value",
+];
+
+/// Scans raw SurrealQL for `$name` references, skipping quoted strings.
+fn scan_parameters(sql: &str, out: &mut BTreeSet<String>) {
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                while let Some(n) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == c {
+                        break;
+                    }
+                }
+            }
+            '⟨' => {
+                while let Some(n) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == '⟩' {
+                        break;
+                    }
+                }
+            }
+            '$' => {
+                let mut name = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphanumeric() || n == '_' {
+                        name.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if !name.is_empty() && !RESERVED_PARAMS.contains(&name.as_str()) {
+                    out.insert(name);
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 /// A helper function that recursively renders a `Condition` tree into a SQL string.
 fn render_condition(condition: &Condition) -> String {
     match condition {
-        Condition::Simple(s) => s.clone(),
-        Condition::And(conditions) => {
-            let rendered: Vec<String> = conditions.iter().map(render_condition).collect();
-            format!("({})", rendered.join(" AND "))
+        Condition::Simple(s) | Condition::Raw(s) => s.clone(),
+        Condition::Binary(field, op, value) => {
+            format!("{} {} {}", field, op.as_surql(), value.to_surql())
+        }
+        Condition::And(_) | Condition::Or(_) => {
+            let (members, joiner) = group_members(condition).expect("condition is a group");
+            match members.as_slice() {
+                // empty groups are the identity of their operator
+                [] if joiner == " AND " => "true".to_string(),
+                [] => "false".to_string(),
+                [only] => render_condition(only),
+                _ => {
+                    let rendered: Vec<String> = members.into_iter().map(render_condition).collect();
+                    format!("({})", rendered.join(joiner))
+                }
+            }
         }
-        Condition::Or(conditions) => {
-            let rendered: Vec<String> = conditions.iter().map(render_condition).collect();
-            format!("({})", rendered.join(" OR "))
+        Condition::Not(inner) => match group_members(inner) {
+            // groups of two or more already render their own parentheses
+            Some((members, _)) if members.len() > 1 => format!("!{}", render_condition(inner)),
+            _ => format!("!({})", render_condition(inner)),
+        },
+        Condition::In(field, values) => {
+            format!("{} IN {}", field, Value::Array(values.clone()).to_surql())
         }
     }
 }
 
-#[derive(Debug, Default)]
+/// Returns the deduplicated members of an `And`/`Or` group together with the
+/// joiner, with nested groups of the same operator flattened into it so that
+/// `(a AND (b AND c))` renders as `(a AND b AND c)`.
+fn group_members(condition: &Condition) -> Option<(Vec<&Condition>, &'static str)> {
+    let (conditions, and, joiner) = match condition {
+        Condition::And(conditions) => (conditions, true, " AND "),
+        Condition::Or(conditions) => (conditions, false, " OR "),
+        _ => return None,
+    };
+    let mut flat = Vec::with_capacity(conditions.len());
+    flatten_group(conditions, and, &mut flat);
+    Some((dedup_conditions(flat), joiner))
+}
+
+fn flatten_group<'a>(conditions: &'a [Condition], and: bool, out: &mut Vec<&'a Condition>) {
+    for c in conditions {
+        match (c, and) {
+            (Condition::And(inner), true) | (Condition::Or(inner), false) => {
+                flatten_group(inner, and, out)
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Collapses runs of whitespace so that `"tenant =  $t"` and `"tenant = $t"`
+/// compare equal.
+fn normalize_simple(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drops conditions that are semantically identical to an earlier sibling,
+/// keeping the first occurrence. Layered filters (e.g. a tenant guard added by
+/// several middlewares) would otherwise render the same predicate repeatedly.
+fn dedup_conditions<'a>(conditions: impl IntoIterator<Item = &'a Condition>) -> Vec<&'a Condition> {
+    let mut kept: Vec<&Condition> = Vec::new();
+    for c in conditions {
+        let duplicate = kept.iter().any(|k| match (k, c) {
+            (
+                Condition::Simple(a) | Condition::Raw(a),
+                Condition::Simple(b) | Condition::Raw(b),
+            ) => normalize_simple(a) == normalize_simple(b),
+            _ => *k == c,
+        });
+        if !duplicate {
+            kept.push(c);
+        }
+    }
+    kept
+}
+
+/// An error returned when a builder cannot render a valid statement.
+///
+/// Compares equal to a `&str` holding its message, so callers can match on
+/// the message while frameworks surface the suggestion to developers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildError {
+    /// What went wrong.
+    pub message: &'static str,
+    /// A machine-readable hint on how to fix the builder chain, e.g.
+    /// `call .from(table) before .build()`.
+    pub suggestion: Option<&'static str>,
+}
+
+impl BuildError {
+    /// Creates an error without a suggestion.
+    pub const fn new(message: &'static str) -> Self {
+        Self {
+            message,
+            suggestion: None,
+        }
+    }
+
+    /// Attaches a suggestion.
+    pub const fn with_suggestion(mut self, suggestion: &'static str) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, " Suggestion: {}.", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl PartialEq<&str> for BuildError {
+    fn eq(&self, other: &&str) -> bool {
+        self.message == *other
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct QueryBuilder {
     /// SELECT items (defaults to ["*"])
     select_items: Vec<String>,
@@ -35,12 +507,24 @@ pub struct QueryBuilder {
     group_all: bool,
     /// Whether to include DISTINCT in the SELECT clause.
     distinct: bool,
-    from_table: Option<String>,
+    from_table: Option<Target>,
     fetch_clauses: Vec<String>,
+    /// Aliases fetched with `fetch_alias`, checked against the select list.
+    fetch_aliases: Vec<String>,
     where_clauses: Vec<Condition>,
+    /// Post-aggregation filters, applied by wrapping the grouped query.
+    having_clauses: Vec<Condition>,
     order_by: Vec<String>,
     limit: Option<u64>,
     start: Option<u64>,
+    /// Whether to include TEMPFILES so large sorts can spill to disk.
+    tempfiles: bool,
+    /// `Some(full)` when EXPLAIN (or EXPLAIN FULL) was requested.
+    explain: Option<bool>,
+    /// Values for `$name` parameters, passed to the driver alongside the query.
+    bindings: BTreeMap<String, Value>,
+    /// Whether `Condition::Simple` is rejected at build time.
+    strict: bool,
 }
 
 impl QueryBuilder {
@@ -56,14 +540,14 @@ impl QueryBuilder {
         qb
     }
 
-    /// Adds a field or expression to select, with optional alias.
-    /// Example: `.select("col", Some("alias"))` yields `col AS alias`.
+    /// Adds a field or expression to select, with optional alias (escaped if
+    /// needed). Example: `.select("col", Some("alias"))` yields `col AS alias`.
     pub fn select(&mut self, expr: &str, alias: Option<&str>) -> &mut Self {
         if self.select_items.len() == 1 && self.select_items[0] == "*" {
             self.select_items.clear();
         }
         let item = if let Some(a) = alias {
-            format!("{} AS {}", expr, a)
+            format!("{} AS {}", expr, escape_ident(a))
         } else {
             expr.to_string()
         };
@@ -71,9 +555,178 @@ impl QueryBuilder {
         self
     }
 
-    /// Sets the table to select data FROM. This is a required clause.
-    pub fn from(&mut self, table: &str) -> &mut Self {
-        self.from_table = Some(table.to_string());
+    /// Adds each field as its own select item, e.g.
+    /// `.select_fields(&["id", "name"])` yields `SELECT id, name`.
+    pub fn select_fields(&mut self, fields: &[&str]) -> &mut Self {
+        for field in fields {
+            self.select(field, None);
+        }
+        self
+    }
+
+    /// Selects a single field by name, escaping it if needed, e.g. `group`
+    /// renders as `` `group` ``.
+    pub fn select_ident(&mut self, field: impl Into<Ident>, alias: Option<&str>) -> &mut Self {
+        self.select(&field.into().to_surql(), alias)
+    }
+
+    /// Adds a `Path` projection (e.g. `tags[0]` or `items[WHERE qty > 1]`) to
+    /// the select list, with optional alias.
+    pub fn select_path(&mut self, path: &Path, alias: Option<&str>) -> &mut Self {
+        self.select(&path.to_surql(), alias)
+    }
+
+    /// Adds a parenthesised subquery to the select list, with optional alias.
+    /// Returns Err if the subquery cannot be built.
+    pub fn select_subquery(
+        &mut self,
+        qb: &QueryBuilder,
+        alias: Option<&str>,
+    ) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.select(&format!("({})", q), alias))
+    }
+
+    /// Emulates a join over a record-link field: adds `qb` as a subquery
+    /// projection filtered by `id = $parent.<link_field>`, e.g.
+    /// `(SELECT name FROM company WHERE id = $parent.employer) AS company`.
+    /// Unlike FETCH, the joined rows can be filtered, ordered and projected.
+    pub fn select_join(
+        &mut self,
+        qb: &QueryBuilder,
+        link_field: &str,
+        alias: Option<&str>,
+    ) -> Result<&mut Self, BuildError> {
+        let mut joined = qb.clone();
+        joined.where_complex(Condition::eq("id", parent_field(link_field)));
+        self.select_subquery(&joined, alias)
+    }
+
+    /// Like [`select_join`](Self::select_join) for fields holding an array of
+    /// record links, filtering by `id IN $parent.<link_field>`.
+    pub fn select_join_many(
+        &mut self,
+        qb: &QueryBuilder,
+        link_field: &str,
+        alias: Option<&str>,
+    ) -> Result<&mut Self, BuildError> {
+        let mut joined = qb.clone();
+        joined.where_complex(Condition::binary(
+            "id",
+            Operator::In,
+            parent_field(link_field),
+        ));
+        self.select_subquery(&joined, alias)
+    }
+
+    /// Adds `count()` to the select list, with optional alias.
+    pub fn select_count(&mut self, alias: Option<&str>) -> &mut Self {
+        self.select("count()", alias)
+    }
+
+    /// Adds `math::sum(field)` to the select list, with optional alias.
+    pub fn select_sum(&mut self, field: &str, alias: Option<&str>) -> &mut Self {
+        self.select(&format!("math::sum({})", field), alias)
+    }
+
+    /// Adds `math::mean(field)` to the select list, with optional alias.
+    pub fn select_avg(&mut self, field: &str, alias: Option<&str>) -> &mut Self {
+        self.select(&format!("math::mean({})", field), alias)
+    }
+
+    /// Adds `math::min(field)` to the select list, with optional alias.
+    pub fn select_min(&mut self, field: &str, alias: Option<&str>) -> &mut Self {
+        self.select(&format!("math::min({})", field), alias)
+    }
+
+    /// Adds `math::max(field)` to the select list, with optional alias.
+    pub fn select_max(&mut self, field: &str, alias: Option<&str>) -> &mut Self {
+        self.select(&format!("math::max({})", field), alias)
+    }
+
+    /// Adds `search::score(ref)` to the select list, with optional alias. Use
+    /// the same reference number as the `Condition::matches` predicate.
+    pub fn select_search_score(&mut self, reference: u32, alias: Option<&str>) -> &mut Self {
+        self.select(&format!("search::score({})", reference), alias)
+    }
+
+    /// Adds `search::highlight(prefix, suffix, ref)` to the select list, with
+    /// optional alias, wrapping matched terms in `prefix` and `suffix`.
+    pub fn select_search_highlight(
+        &mut self,
+        prefix: &str,
+        suffix: &str,
+        reference: u32,
+        alias: Option<&str>,
+    ) -> &mut Self {
+        self.select(
+            &format!(
+                "search::highlight({}, {}, {})",
+                quote_string(prefix),
+                quote_string(suffix),
+                reference
+            ),
+            alias,
+        )
+    }
+
+    /// Adds a nested destructuring projection, e.g.
+    /// `.select_nested("address", &["city", "country"])` yields
+    /// `address.{city, country}`. The base expression is used verbatim so it
+    /// may also be a graph traversal like `->likes->post`.
+    pub fn select_nested(&mut self, base: &str, fields: &[&str]) -> &mut Self {
+        self.select(
+            &format!("{}.{{{}}}", base, render_destructure(fields)),
+            None,
+        )
+    }
+
+    /// Sets the target to select data FROM. This is a required clause.
+    ///
+    /// Accepts a table name or another `QueryBuilder`, which is rendered as a
+    /// parenthesised subquery: `SELECT * FROM (SELECT ...)`.
+    pub fn from(&mut self, target: impl Into<Target>) -> &mut Self {
+        self.from_table = Some(target.into());
+        self
+    }
+
+    /// Selects FROM a pre-bound parameter, e.g. `SELECT * FROM $things`.
+    /// The leading `$` is optional.
+    pub fn from_param(&mut self, name: &str) -> &mut Self {
+        self.from_table = Some(Target::Param(name.trim_start_matches('$').to_string()));
+        self
+    }
+
+    /// Selects over a half-open range of record ids, e.g. `person:1..1000`.
+    pub fn from_range(
+        &mut self,
+        table: &str,
+        start: impl Into<RecordIdKey>,
+        end: impl Into<RecordIdKey>,
+    ) -> &mut Self {
+        self.from_table = Some(Target::Table(format!(
+            "{}:{}..{}",
+            table,
+            start.into().to_surql(),
+            end.into().to_surql()
+        )));
+        self
+    }
+
+    /// Selects over an inclusive range of record ids, e.g.
+    /// `temperature:['London', d'2024-01-01']..=['London', d'2024-02-01']`.
+    pub fn from_range_inclusive(
+        &mut self,
+        table: &str,
+        start: impl Into<RecordIdKey>,
+        end: impl Into<RecordIdKey>,
+    ) -> &mut Self {
+        self.from_table = Some(Target::Table(format!(
+            "{}:{}..={}",
+            table,
+            start.into().to_surql(),
+            end.into().to_surql()
+        )));
         self
     }
 
@@ -83,6 +736,25 @@ impl QueryBuilder {
         self
     }
 
+    /// Adds a FETCH clause for a single field, escaping it if needed.
+    pub fn fetch_ident(&mut self, field: impl Into<Ident>) -> &mut Self {
+        self.fetch(&field.into().to_surql())
+    }
+
+    /// Adds a FETCH clause for a field path such as `author.company`, with
+    /// each segment escaped.
+    pub fn fetch_path(&mut self, path: impl Into<Path>) -> &mut Self {
+        self.fetch(&path.into().to_surql())
+    }
+
+    /// Fetches the records behind a select-list alias, such as one given to
+    /// a traversal. `build()` returns Err if no select item has that alias.
+    pub fn fetch_alias(&mut self, alias: &str) -> &mut Self {
+        let alias = escape_ident(alias);
+        self.fetch_aliases.push(alias.clone());
+        self.fetch(&alias)
+    }
+
     /// Adds a graph traversal or complex projection to the SELECT list.
     pub fn graph_expand(&mut self, expansion_clause: &str) -> &mut Self {
         self.graph_expansions.push(expansion_clause.to_string());
@@ -90,7 +762,9 @@ impl QueryBuilder {
     }
 
     /// A convenience shortcut to add a simple, raw condition string.
-    /// This is equivalent to `add_condition(Condition::Simple(...))`.
+    /// This is equivalent to `where_complex(Condition::Simple(...))`. The text
+    /// is not escaped; prefer typed conditions, or [`Raw`] to mark trusted SQL
+    /// explicitly.
     pub fn where_simple(&mut self, condition: &str) -> &mut Self {
         self.where_clauses
             .push(Condition::Simple(condition.to_string()));
@@ -99,8 +773,56 @@ impl QueryBuilder {
 
     /// Adds a complex `Condition` to the WHERE clause. All top-level
     /// conditions are joined by AND.
-    pub fn where_complex(&mut self, condition: Condition) -> &mut Self {
-        self.where_clauses.push(condition);
+    pub fn where_complex(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        self.where_clauses.push(condition.into());
+        self
+    }
+
+    /// Adds the condition registered under `name` in `registry` to the WHERE
+    /// clause. Returns Err if no such fragment exists.
+    pub fn where_fragment(
+        &mut self,
+        registry: &FragmentRegistry,
+        name: &str,
+    ) -> Result<&mut Self, BuildError> {
+        let condition = registry.get(name).ok_or(
+            BuildError::new("Unknown condition fragment.")
+                .with_suggestion("register the fragment with FragmentRegistry::register first"),
+        )?;
+        Ok(self.where_complex(condition.clone()))
+    }
+
+    /// Adds a `field = value` condition for every entry of the map. Entries are
+    /// sorted by field name so the rendered query is identical across runs,
+    /// even for a `HashMap`.
+    pub fn where_map<K: AsRef<str>, V: Into<Value>>(
+        &mut self,
+        map: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self {
+        let mut entries: Vec<(K, V)> = map.into_iter().collect();
+        entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        for (field, value) in entries {
+            self.where_complex(Condition::eq(field.as_ref(), value));
+        }
+        self
+    }
+
+    /// Adds a `field IN [values]` condition, rendering each value as an
+    /// escaped literal. Equivalent to `where_complex(Condition::is_in(...))`.
+    pub fn where_in<V: Into<Value>>(
+        &mut self,
+        field: impl Into<Path>,
+        values: impl IntoIterator<Item = V>,
+    ) -> &mut Self {
+        self.where_complex(Condition::is_in(field, values))
+    }
+
+    /// Filters on aggregated results, like SQL's HAVING. SurrealQL has no
+    /// HAVING clause, so `build()` wraps the grouped query in an outer
+    /// `SELECT * FROM (...) WHERE ...`; ORDER BY, LIMIT, START and FETCH then
+    /// apply to the outer query. Can be called multiple times.
+    pub fn having(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        self.having_clauses.push(condition.into());
         self
     }
 
@@ -110,6 +832,37 @@ impl QueryBuilder {
         self
     }
 
+    /// Adds a typed ORDER BY entry, e.g. `name DESC`.
+    pub fn order_by_field(&mut self, field: &str, order: Order) -> &mut Self {
+        self.order_by.push(format!("{} {}", field, order.as_str()));
+        self
+    }
+
+    /// Adds an ORDER BY entry for a single field, escaping it if needed.
+    pub fn order_by_ident(&mut self, field: impl Into<Ident>, order: Order) -> &mut Self {
+        self.order_by_field(&field.into().to_surql(), order)
+    }
+
+    /// Adds an ORDER BY entry that sorts strings numerically, e.g. `name NUMERIC ASC`.
+    pub fn order_by_numeric(&mut self, field: &str, order: Order) -> &mut Self {
+        self.order_by
+            .push(format!("{} NUMERIC {}", field, order.as_str()));
+        self
+    }
+
+    /// Adds an ORDER BY entry using Unicode collation, e.g. `name COLLATE ASC`.
+    pub fn order_by_collate(&mut self, field: &str, order: Order) -> &mut Self {
+        self.order_by
+            .push(format!("{} COLLATE {}", field, order.as_str()));
+        self
+    }
+
+    /// Orders the results randomly with `ORDER BY RAND()`.
+    pub fn order_by_rand(&mut self) -> &mut Self {
+        self.order_by.push("RAND()".to_string());
+        self
+    }
+
     /// Add a GROUP BY field expression. Can be called multiple times to group by multiple fields.
     pub fn group_by(&mut self, expr: &str) -> &mut Self {
         self.group_by_fields.push(expr.to_string());
@@ -134,45 +887,221 @@ impl QueryBuilder {
         self
     }
 
+    /// Sets LIMIT and START for a 1-based page number. Page 0 is treated as
+    /// the first page, e.g. `.paginate(3, 20)` yields `LIMIT 20 START 40`.
+    pub fn paginate(&mut self, page: u64, per_page: u64) -> &mut Self {
+        let offset = page.saturating_sub(1).saturating_mul(per_page);
+        self.limit(per_page).start(offset)
+    }
+
+    /// Adds the TEMPFILES keyword so SurrealDB processes the query on disk
+    /// rather than in memory, e.g. for large ORDER BY reporting queries.
+    pub fn tempfiles(&mut self) -> &mut Self {
+        self.tempfiles = true;
+        self
+    }
+
+    /// Appends EXPLAIN so SurrealDB returns the query plan instead of results.
+    pub fn explain(&mut self) -> &mut Self {
+        self.explain = Some(false);
+        self
+    }
+
+    /// Appends EXPLAIN FULL, which also reports the number of fetched records.
+    pub fn explain_full(&mut self) -> &mut Self {
+        self.explain = Some(true);
+        self
+    }
+
     /// Enables DISTINCT in the SELECT clause.
     pub fn distinct(&mut self) -> &mut Self {
         self.distinct = true;
         self
     }
 
-    pub fn build(&self) -> Result<String, &'static str> {
-        let from_table = self
-            .from_table
-            .as_ref()
-            .ok_or("The FROM clause is required.")?;
-
-        let mut all_selects = self.select_items.clone();
-        all_selects.extend(self.graph_expansions.iter().cloned());
-        let final_select_clause = all_selects.join(", ");
+    /// Rejects unescaped `Condition::Simple` strings (including those from
+    /// `where_simple`) at build time, so teams can migrate to typed conditions
+    /// and `Condition::raw` for the trusted remainder.
+    pub fn strict(&mut self) -> &mut Self {
+        self.strict = true;
+        self
+    }
 
-        let mut query = if self.distinct {
-            format!(
-                "SELECT DISTINCT {} FROM {}",
-                final_select_clause, from_table
-            )
-        } else {
-            format!("SELECT {} FROM {}", final_select_clause, from_table)
-        };
-        if !self.traverse_clauses.is_empty() {
-            for clause in &self.traverse_clauses {
-                query.push(' ');
-                query.push_str(clause);
+    /// Returns the names (without `$`) of every parameter this query expects to
+    /// be bound externally: a `$param` FROM target, parameters referenced in
+    /// WHERE conditions, and those of any subquery.
+    pub fn parameters(&self) -> BTreeSet<String> {
+        let mut out = BTreeSet::new();
+        match &self.from_table {
+            Some(Target::Param(name)) => {
+                out.insert(name.clone());
             }
+            Some(Target::Subquery(qb)) => out.extend(qb.parameters()),
+            _ => {}
         }
-
-        if !self.where_clauses.is_empty() {
-            let rendered: Vec<String> = self
-                .where_clauses
-                .iter()
-                .map(|c| render_condition(c))
-                .collect();
-            query.push_str(" WHERE ");
-            query.push_str(&rendered.join(" AND "));
+        for c in self.where_clauses.iter().chain(&self.having_clauses) {
+            c.collect_parameters(&mut out);
+        }
+        out
+    }
+
+    /// Binds a value to the `$name` parameter instead of inlining it in the
+    /// query text. A leading `$` is optional; binding a name again replaces it.
+    pub fn bind(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        self.bindings
+            .insert(name.trim_start_matches('$').to_string(), value.into());
+        self
+    }
+
+    /// The bound parameters, including those of a subquery target (bindings
+    /// on this builder take precedence).
+    pub fn bindings(&self) -> BTreeMap<String, Value> {
+        let mut out = match &self.from_table {
+            Some(Target::Subquery(qb)) => qb.bindings(),
+            _ => BTreeMap::new(),
+        };
+        out.extend(self.bindings.clone());
+        out
+    }
+
+    /// Builds the query with every literal operand of a typed WHERE or HAVING
+    /// condition (including those of a subquery target) replaced by a
+    /// generated `$pN` parameter, returning the query text and all bindings,
+    /// ready to hand to a driver. Raw and simple conditions are left as is.
+    pub fn build_with_bindings(&self) -> Result<(String, BTreeMap<String, Value>), BuildError> {
+        let mut qb = self.clone();
+        let mut bindings = self.bindings();
+        let mut next = 0;
+        qb.parameterize(&mut next, &mut bindings);
+        Ok((qb.build()?, bindings))
+    }
+
+    fn parameterize(&mut self, next: &mut usize, bindings: &mut BTreeMap<String, Value>) {
+        if let Some(Target::Subquery(qb)) = &mut self.from_table {
+            qb.parameterize(next, bindings);
+        }
+        for c in self
+            .where_clauses
+            .iter_mut()
+            .chain(&mut self.having_clauses)
+        {
+            c.parameterize(next, bindings);
+        }
+    }
+
+    /// The parameters from `parameters()` that have no bound value yet.
+    pub fn unbound_parameters(&self) -> BTreeSet<String> {
+        let bindings = self.bindings();
+        self.parameters()
+            .into_iter()
+            .filter(|p| !bindings.contains_key(p))
+            .collect()
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Select
+    }
+
+    /// Returns a heuristic cost score for this query, so gateways can reject
+    /// or deprioritise expensive user-generated queries before running them.
+    ///
+    /// The score starts at 1 and adds 2 per graph hop in the projections, 3 per
+    /// FETCH clause, 10 for an unbounded table scan (no WHERE and no LIMIT on
+    /// a table target) and the score of any subquery target. The weights are
+    /// relative; only compare scores against each other or a chosen threshold.
+    pub fn complexity(&self) -> u32 {
+        let mut score: u32 = 1;
+
+        let hops: usize = self
+            .select_items
+            .iter()
+            .chain(&self.graph_expansions)
+            .chain(&self.traverse_clauses)
+            .map(|s| {
+                let both = s.matches("<->").count();
+                let rest = s.replace("<->", "");
+                both + rest.matches("->").count() + rest.matches("<-").count()
+            })
+            .sum();
+        score += 2 * hops as u32;
+        score += 3 * self.fetch_clauses.len() as u32;
+
+        match &self.from_table {
+            Some(Target::Subquery(qb)) => score += qb.complexity(),
+            Some(Target::Table(t))
+                if !t.contains(':') && self.where_clauses.is_empty() && self.limit.is_none() =>
+            {
+                score += 10;
+            }
+            _ => {}
+        }
+        score
+    }
+
+    pub fn build(&self) -> Result<String, BuildError> {
+        let from_table = self
+            .from_table
+            .as_ref()
+            .ok_or(
+                BuildError::new("The FROM clause is required.")
+                    .with_suggestion("call .from(table) before .build()"),
+            )?
+            .render()?;
+
+        if self.strict
+            && self
+                .where_clauses
+                .iter()
+                .chain(&self.having_clauses)
+                .any(Condition::contains_simple)
+        {
+            return Err(
+                BuildError::new("Simple conditions are not allowed in strict mode.")
+                    .with_suggestion("use Condition::binary or a typed constructor, or Condition::raw for trusted SQL"),
+            );
+        }
+
+        let mut all_selects = self.select_items.clone();
+        all_selects.extend(self.graph_expansions.iter().cloned());
+        let unknown_alias = self.fetch_aliases.iter().any(|alias| {
+            !all_selects
+                .iter()
+                .chain(&self.traverse_clauses)
+                .any(|item| item.rsplit_once(" AS ").is_some_and(|(_, a)| a == alias))
+        });
+        if unknown_alias {
+            return Err(BuildError::new(
+                "FETCH references an alias that is not in the SELECT list.",
+            )
+            .with_suggestion(
+                "select the field or traversal with that alias, or use .fetch() for plain fields",
+            ));
+        }
+        let final_select_clause = all_selects.join(", ");
+
+        let mut query = if self.distinct {
+            format!(
+                "SELECT DISTINCT {} FROM {}",
+                final_select_clause, from_table
+            )
+        } else {
+            format!("SELECT {} FROM {}", final_select_clause, from_table)
+        };
+        if !self.traverse_clauses.is_empty() {
+            for clause in &self.traverse_clauses {
+                query.push(' ');
+                query.push_str(clause);
+            }
+        }
+
+        if !self.where_clauses.is_empty() {
+            let rendered: Vec<String> = dedup_conditions(&self.where_clauses)
+                .into_iter()
+                .map(render_condition)
+                .collect();
+            query.push_str(" WHERE ");
+            query.push_str(&rendered.join(" AND "));
         }
 
         // GROUP BY / GROUP ALL
@@ -183,6 +1112,14 @@ impl QueryBuilder {
             query.push_str(&self.group_by_fields.join(", "));
         }
 
+        if !self.having_clauses.is_empty() {
+            let rendered: Vec<String> = dedup_conditions(&self.having_clauses)
+                .into_iter()
+                .map(render_condition)
+                .collect();
+            query = format!("SELECT * FROM ({}) WHERE {}", query, rendered.join(" AND "));
+        }
+
         if !self.order_by.is_empty() {
             query.push_str(" ORDER BY ");
             query.push_str(&self.order_by.join(", "));
@@ -201,32 +1138,249 @@ impl QueryBuilder {
             query.push_str(&self.fetch_clauses.join(", "));
         }
 
+        if self.tempfiles {
+            query.push_str(" TEMPFILES");
+        }
+
+        match self.explain {
+            Some(true) => query.push_str(" EXPLAIN FULL"),
+            Some(false) => query.push_str(" EXPLAIN"),
+            None => {}
+        }
+
         Ok(query)
     }
 
-    /// Add a two-step graph traversal with optional alias.
+    /// Add a two-step graph traversal with optional projection and alias.
+    /// Each hop renders its own direction. For longer chains see
+    /// [`Traversal`].
     pub fn graph_traverse(&mut self, params: GraphExpandParams) -> &mut Self {
-        let mut clause = String::new();
-        let (ref dir1, ref tbl1) = params.from;
-        clause.push_str(match dir1 {
-            Direction::Out => "->",
-            Direction::In => "<-",
-        });
-        clause.push_str(tbl1);
-        let tbl2 = &params.to.1;
-        clause.push_str("->");
-        clause.push_str(tbl2);
-        clause.push_str(".*");
+        let (dir1, tbl1) = params.from;
+        let (dir2, tbl2) = params.to;
+        let traversal = Traversal::new().step(dir1, &tbl1).step(dir2, &tbl2);
+        let traversal = match params.projection.as_deref() {
+            Some("*") => traversal.all_fields(),
+            Some(field) => traversal.field(field),
+            None => traversal,
+        };
+        let mut clause = traversal.to_surql();
         if let Some(ref a) = params.alias {
             clause.push_str(" AS ");
-            clause.push_str(a);
+            clause.push_str(&escape_ident(a));
         }
         self.traverse_clauses.push(clause);
         self
     }
 }
 
-/// Helper to build a SurrealQL script composed of `LET` assignments and a final `RETURN` object.
+/// The target of a FROM clause.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// A table name, or any other target rendered verbatim.
+    Table(String),
+    /// A nested query, rendered in parentheses.
+    Subquery(Box<QueryBuilder>),
+    /// An externally bound parameter (stored without the leading `$`).
+    Param(String),
+}
+
+impl Target {
+    /// Wraps an already rendered query in parentheses to use it as a subquery.
+    pub fn raw_subquery(query: &str) -> Self {
+        Target::Table(format!("({})", query))
+    }
+
+    fn render(&self) -> Result<String, BuildError> {
+        match self {
+            Target::Table(t) => Ok(t.clone()),
+            Target::Subquery(qb) => Ok(format!("({})", qb.build()?)),
+            Target::Param(name) => Ok(format!("${}", name)),
+        }
+    }
+}
+
+impl From<&str> for Target {
+    fn from(table: &str) -> Self {
+        Target::Table(table.to_string())
+    }
+}
+
+impl From<String> for Target {
+    fn from(table: String) -> Self {
+        Target::Table(table)
+    }
+}
+
+impl From<RecordId> for Target {
+    fn from(id: RecordId) -> Self {
+        Target::Table(id.to_surql())
+    }
+}
+
+impl<K: Into<RecordIdKey>> From<(&str, K)> for Target {
+    fn from(id: (&str, K)) -> Self {
+        RecordId::from(id).into()
+    }
+}
+
+impl From<&RecordId> for Target {
+    fn from(id: &RecordId) -> Self {
+        Target::Table(id.to_surql())
+    }
+}
+
+impl From<&QueryBuilder> for Target {
+    fn from(qb: &QueryBuilder) -> Self {
+        Target::Subquery(Box::new(qb.clone()))
+    }
+}
+
+impl From<&mut QueryBuilder> for Target {
+    fn from(qb: &mut QueryBuilder) -> Self {
+        Target::Subquery(Box::new(qb.clone()))
+    }
+}
+
+impl From<QueryBuilder> for Target {
+    fn from(qb: QueryBuilder) -> Self {
+        Target::Subquery(Box::new(qb))
+    }
+}
+
+/// A registry of named, reusable condition fragments, so business rules such
+/// as "visible to the current user" are defined once and rendered the same way
+/// by every builder that references them.
+///
+/// Fragments are parameterised with `$params` (e.g. `owner = $user`), which
+/// are bound when the query is executed; use [`FragmentRegistry::parameters`]
+/// to discover them.
+#[derive(Debug, Default, Clone)]
+pub struct FragmentRegistry {
+    fragments: BTreeMap<String, Condition>,
+}
+
+impl FragmentRegistry {
+    /// Create a new empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register (or replace) a fragment under `name`.
+    pub fn register(&mut self, name: &str, condition: Condition) -> &mut Self {
+        self.fragments.insert(name.to_string(), condition);
+        self
+    }
+
+    /// Look up a fragment by name.
+    pub fn get(&self, name: &str) -> Option<&Condition> {
+        self.fragments.get(name)
+    }
+
+    /// The parameters a fragment expects to be bound, or None if it is not
+    /// registered.
+    pub fn parameters(&self, name: &str) -> Option<BTreeSet<String>> {
+        self.get(name).map(Condition::parameters)
+    }
+}
+
+/// A value in a script's RETURN object: a verbatim expression, a nested
+/// object or array, or an embedded subquery. Every part is checked in
+/// read-only mode.
+///
+/// ```
+/// use surrealex::{QueryBuilder, ReturnValue, ScriptBuilder};
+///
+/// let mut recent = QueryBuilder::new();
+/// recent.from("post").limit(3);
+/// let mut sb = ScriptBuilder::new();
+/// sb.returning(vec![
+///     (
+///         "data",
+///         ReturnValue::object(vec![("items", "$x"), ("count", "$y")]),
+///     ),
+///     ("recent", ReturnValue::query(&recent).unwrap()),
+///     ("flags", ReturnValue::array(vec!["$a", "$b"])),
+/// ]);
+/// assert_eq!(
+///     sb.build().unwrap(),
+///     "RETURN { data: { items: $x, count: $y }, recent: (SELECT * FROM post LIMIT 3), \
+///      flags: [$a, $b] }; "
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnValue {
+    /// A verbatim expression such as `$product` or `count($items)`.
+    Expr(String),
+    /// A nested object; keys are quoted when they are not plain identifiers.
+    Object(Vec<(String, ReturnValue)>),
+    /// An array of values.
+    Array(Vec<ReturnValue>),
+    /// A built subquery, rendered in parentheses.
+    Query(String),
+}
+
+impl ReturnValue {
+    /// A nested object from (key, value) pairs.
+    pub fn object<V: Into<ReturnValue>>(fields: Vec<(&str, V)>) -> Self {
+        ReturnValue::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.into()))
+                .collect(),
+        )
+    }
+
+    /// An array of values.
+    pub fn array<V: Into<ReturnValue>>(items: Vec<V>) -> Self {
+        ReturnValue::Array(items.into_iter().map(Into::into).collect())
+    }
+
+    /// An embedded subquery. Returns Err if the query cannot be built.
+    pub fn query(qb: &QueryBuilder) -> Result<Self, BuildError> {
+        Ok(ReturnValue::Query(qb.build()?))
+    }
+
+    /// Renders the value as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        match self {
+            ReturnValue::Expr(expr) => expr.clone(),
+            ReturnValue::Object(fields) => {
+                let pairs: Vec<String> = fields
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", render_object_key(k), v.to_surql()))
+                    .collect();
+                format!("{{ {} }}", pairs.join(", "))
+            }
+            ReturnValue::Array(items) => {
+                let items: Vec<String> = items.iter().map(ReturnValue::to_surql).collect();
+                format!("[{}]", items.join(", "))
+            }
+            ReturnValue::Query(q) => format!("({})", q),
+        }
+    }
+}
+
+impl From<&str> for ReturnValue {
+    fn from(expr: &str) -> Self {
+        ReturnValue::Expr(expr.to_string())
+    }
+}
+
+impl From<String> for ReturnValue {
+    fn from(expr: String) -> Self {
+        ReturnValue::Expr(expr)
+    }
+}
+
+impl From<Value> for ReturnValue {
+    /// Renders the value as a literal.
+    fn from(value: Value) -> Self {
+        ReturnValue::Expr(value.to_surql())
+    }
+}
+
+/// Helper to build a SurrealQL script composed of `LET` assignments and an optional final
+/// `RETURN` object.
 ///
 /// Example:
 /// ```rust,ignore
@@ -238,7 +1392,14 @@ impl QueryBuilder {
 #[derive(Debug, Default)]
 pub struct ScriptBuilder {
     statements: Vec<String>,
-    return_map: Option<Vec<(String, String)>>,
+    /// The expressions assigned by the LET statements, checked in read-only mode.
+    exprs: Vec<String>,
+    /// The rendered RETURN expression, if any.
+    ret: Option<String>,
+    read_only: bool,
+    require_return: bool,
+    comments: CommentMode,
+    bindings: BTreeMap<String, Value>,
 }
 
 impl ScriptBuilder {
@@ -246,31 +1407,114 @@ impl ScriptBuilder {
     pub fn new() -> Self {
         Self {
             statements: Vec::new(),
-            return_map: None,
+            exprs: Vec::new(),
+            ret: None,
+            read_only: false,
+            require_return: false,
+            comments: CommentMode::Preserve,
+            bindings: BTreeMap::new(),
         }
     }
 
+    /// Binds a value to the `$name` parameter; a leading `$` is optional.
+    pub fn bind(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        self.bindings
+            .insert(name.trim_start_matches('$').to_string(), value.into());
+        self
+    }
+
+    /// The bound parameters.
+    pub fn bindings(&self) -> &BTreeMap<String, Value> {
+        &self.bindings
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Script
+    }
+
+    /// Set whether comments (from `comment()` or inside raw fragments) are
+    /// kept or stripped from the output.
+    pub fn comments(&mut self, mode: CommentMode) -> &mut Self {
+        self.comments = mode;
+        self
+    }
+
+    /// Add a `-- text` comment line, one per line of `text`.
+    pub fn comment(&mut self, text: &str) -> &mut Self {
+        self.statements.push(render_comment(text));
+        self
+    }
+
+    /// Restrict the script to read-only statements. `build()` then fails if
+    /// any assigned expression is a statement other than SELECT, INFO or
+    /// LIVE, contains a `;` or unbalanced parentheses, or mentions a write
+    /// keyword (CREATE, UPDATE, DELETE, ...), `fn::` or `http::` outside a
+    /// string or escaped identifier. The check is conservative: a field
+    /// named like a keyword must be escaped to pass.
+    ///
+    /// `TransactionBuilder` has no read-only mode since a transaction exists
+    /// to group writes; for read-only batches, add read-only scripts to a
+    /// `BatchBuilder`, which validates each script as it is added.
+    pub fn read_only(&mut self) -> &mut Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Make `build()` fail unless a return value was set with `returning()`,
+    /// `return_expr()` or `return_query()`.
+    /// Without it, scripts with no return object (e.g. side-effect-only seed
+    /// scripts) are built without a RETURN statement.
+    pub fn require_return(&mut self) -> &mut Self {
+        self.require_return = true;
+        self
+    }
+
     /// Add a raw LET assignment where the expression is wrapped in parentheses.
     /// Example: let $name = (SELECT * FROM t WHERE ...);
     pub fn let_raw(&mut self, name: &str, expr: &str) -> &mut Self {
         let s = format!("LET ${} = ({});", name, expr);
         self.statements.push(s);
+        self.exprs.push(expr.to_string());
+        self
+    }
+
+    /// Add a LET assignment of a literal value, e.g. an object built from a
+    /// map: `LET $config = { retries: 3, tags: ['a', 'b'] };`. `Raw` values
+    /// are treated as expressions and checked in read-only mode.
+    pub fn let_value(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        let value = value.into();
+        if let Value::Raw(expr) = &value {
+            self.exprs.push(expr.clone());
+        }
+        self.statements.push(format!(
+            "LET ${} = {};",
+            name.trim_start_matches('$'),
+            value.to_surql()
+        ));
         self
     }
 
     /// Add a LET assignment where the expression is wrapped in parentheses and
     /// a suffix (like an index or field access) is appended outside the
     /// parentheses. Example suffix: "[0].count" -> (SELECT ...)[0].count
-    pub fn let_raw_with_suffix(&mut self, name: &str, expr: &str, suffix: &str) -> &mut Self {
-        let s = format!("LET ${} = ({}){};", name, expr, suffix);
+    /// The suffix may be a raw string or a typed chain from [`accessor`].
+    pub fn let_raw_with_suffix(
+        &mut self,
+        name: &str,
+        expr: &str,
+        suffix: impl Into<Accessor>,
+    ) -> &mut Self {
+        let s = format!("LET ${} = ({}){};", name, expr, suffix.into().to_surql());
         self.statements.push(s);
+        self.exprs.push(expr.to_string());
         self
     }
 
     /// Accept a `QueryBuilder`, build its query string and create a LET
     /// assignment using the built query. Returns Err if the inner query
     /// cannot be built.
-    pub fn let_query(&mut self, name: &str, qb: &QueryBuilder) -> Result<&mut Self, &'static str> {
+    pub fn let_query(&mut self, name: &str, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
         let q = qb.build()?;
         Ok(self.let_raw(name, &q))
     }
@@ -281,117 +1525,724 @@ impl ScriptBuilder {
         &mut self,
         name: &str,
         qb: &QueryBuilder,
-        suffix: &str,
-    ) -> Result<&mut Self, &'static str> {
+        suffix: impl Into<Accessor>,
+    ) -> Result<&mut Self, BuildError> {
         let q = qb.build()?;
         Ok(self.let_raw_with_suffix(name, &q, suffix))
     }
 
     /// Provide the return mapping as a list of (key, value) pairs. Values are
-    /// verbatim strings (e.g. `$product` or an expression).
-    pub fn returning(&mut self, map: Vec<(&str, &str)>) -> &mut Self {
-        self.return_map = Some(
-            map.into_iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect(),
-        );
+    /// verbatim strings (e.g. `$product` or an expression), or a
+    /// [`ReturnValue`] for nested objects, arrays and subqueries.
+    pub fn returning<V: Into<ReturnValue>>(&mut self, map: Vec<(&str, V)>) -> &mut Self {
+        let ret = ReturnValue::object(map);
+        self.ret = match &ret {
+            ReturnValue::Object(fields) if fields.is_empty() => None,
+            ret => Some(ret.to_surql()),
+        };
         self
     }
 
+    /// End the script with `RETURN <expr>` instead of a return object, e.g.
+    /// `return_expr("$total")`. The expression is checked in read-only mode.
+    pub fn return_expr(&mut self, expr: &str) -> &mut Self {
+        self.ret = Some(expr.to_string());
+        self
+    }
+
+    /// End the script with `RETURN (<query>)`. Returns Err if the query
+    /// cannot be built.
+    pub fn return_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.return_expr(&format!("({})", q)))
+    }
+
     /// Build the final script string.
-    pub fn build(&self) -> Result<String, &'static str> {
-        let ret = match &self.return_map {
-            Some(m) if !m.is_empty() => m,
-            _ => return Err("A return object is required."),
+    pub fn build(&self) -> Result<String, BuildError> {
+        let statements = self.render_statements().collect::<Result<Vec<_>, _>>()?;
+        Ok(statements.join("\n"))
+    }
+
+    /// Render the script one statement at a time, so large scripts can be
+    /// written to disk or the wire incrementally. Joining the items with
+    /// newlines gives the same output as `build()`; a validation error is
+    /// yielded as the only item.
+    pub fn render_statements(&self) -> impl Iterator<Item = Result<String, BuildError>> + '_ {
+        let (error, statements) = match self.validate() {
+            Ok(ret) => {
+                let ret = ret.map(|ret| format!("RETURN {}; ", ret));
+                let statements = self
+                    .statements
+                    .iter()
+                    .cloned()
+                    .chain(ret)
+                    .filter_map(|st| self.comments.apply_statement(st))
+                    .map(Ok);
+                (None, Some(statements))
+            }
+            Err(e) => (Some(Err(e)), None),
         };
+        error.into_iter().chain(statements.into_iter().flatten())
+    }
 
-        let mut out = String::new();
-        for st in &self.statements {
-            out.push_str(st);
-            out.push('\n');
+    /// The statements with comments removed and whitespace trimmed, for
+    /// embedding the script in a one-line block.
+    pub(crate) fn code_statements(&self) -> Result<Vec<String>, BuildError> {
+        let mut out = Vec::new();
+        for st in self.render_statements() {
+            let code = strip_comments(&st?);
+            if !code.is_empty() {
+                out.push(code.trim().to_string());
+            }
         }
-
-        out.push_str("RETURN { ");
-        let pairs: Vec<String> = ret.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
-        out.push_str(&pairs.join(", "));
-        out.push_str(" }; ");
         Ok(out)
     }
+
+    fn validate(&self) -> Result<Option<&String>, BuildError> {
+        let ret = match &self.ret {
+            Some(ret) => Some(ret),
+            None if self.require_return => {
+                return Err(BuildError::new("A return object is required.")
+                    .with_suggestion("call .returning(...) or .return_expr(...) before .build()"));
+            }
+            None => None,
+        };
+
+        if self.read_only && !self.exprs.iter().chain(ret).all(|e| is_read_only_expr(e)) {
+            return Err(BuildError::new(
+                "Read-only scripts may only contain SELECT, INFO or LIVE statements.",
+            )
+            .with_suggestion("move writes to a separate script or drop .read_only()"));
+        }
+        Ok(ret)
+    }
 }
 
-/// Builder for SurrealQL transactions.
-///
-/// Usage: create a TransactionBuilder, call `begin()`, add statements (raw strings,
-/// queries from `QueryBuilder`, or full `ScriptBuilder` scripts), then `commit()` or
-/// `cancel()` and `build()` to get the final SurrealQL transaction script.
-#[derive(Debug, Default)]
-pub struct TransactionBuilder {
-    statements: Vec<String>,
+/// `$parent.<field>`, with the field path escaped, for correlated subqueries.
+fn parent_field(field: &str) -> Raw {
+    Raw::new(format!("$parent.{}", Path::from(field).to_surql()))
 }
 
-impl TransactionBuilder {
-    /// Create a new empty transaction builder.
-    pub fn new() -> Self {
-        Self {
-            statements: Vec::new(),
+/// Keywords that make an expression write, wherever they appear in it.
+const WRITE_KEYWORDS: &[&str] = &[
+    "CREATE", "UPDATE", "UPSERT", "DELETE", "INSERT", "RELATE", "DEFINE", "REMOVE", "ALTER",
+    "KILL", "REBUILD",
+];
+
+/// Whether an expression from a read-only script can only read. Statement
+/// separators, unbalanced parentheses, write keywords and calls to custom
+/// (`fn::`) or `http::` functions outside string literals and escaped
+/// identifiers all count as writes.
+fn is_read_only_expr(expr: &str) -> bool {
+    match StatementKind::of(expr) {
+        StatementKind::Other => {}
+        kind if kind.is_read_only() => {}
+        _ => return false,
+    }
+    let chars: Vec<char> = expr.chars().collect();
+    let mut depth: i32 = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' | '`' => {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            ';' => return false,
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i + 1 < chars.len()
+                    && (chars[i + 1].is_ascii_alphanumeric() || chars[i + 1] == '_')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..=i].iter().collect();
+                let is_param = start > 0 && chars[start - 1] == '$';
+                let in_namespace = start >= 2 && chars[start - 2..start] == [':', ':'];
+                let is_namespace = chars[i + 1..].starts_with(&[':', ':']);
+                if is_param || in_namespace {
+                    // `$update` or `array::insert`.
+                } else if is_namespace {
+                    if word.eq_ignore_ascii_case("fn") || word.eq_ignore_ascii_case("http") {
+                        return false;
+                    }
+                } else if WRITE_KEYWORDS.iter().any(|w| w.eq_ignore_ascii_case(&word)) {
+                    return false;
+                }
+            }
+            _ => {}
         }
+        i += 1;
     }
+    depth == 0
+}
 
-    /// Start the transaction block. Uses `BEGIN TRANSACTION;`.
-    pub fn begin(&mut self) -> &mut Self {
-        self.statements.push("BEGIN TRANSACTION;".to_string());
-        self
+/// Trims a statement and terminates it with a semicolon if missing.
+fn terminate(stmt: &str) -> String {
+    let s = stmt.trim();
+    if s.ends_with(';') {
+        s.to_string()
+    } else {
+        format!("{};", s)
     }
+}
 
-    /// Add a raw statement (will be terminated with a semicolon if missing).
-    pub fn add_statement(&mut self, stmt: &str) -> &mut Self {
-        let s = stmt.trim();
-        if s.ends_with(';') {
-            self.statements.push(s.to_string());
-        } else {
-            self.statements.push(format!("{};", s));
+/// The type of a SurrealQL statement, so middleware, loggers and the
+/// read-only mode can branch on it without sniffing strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// `SELECT`
+    Select,
+    /// `CREATE`
+    Create,
+    /// `UPDATE`
+    Update,
+    /// `UPSERT`
+    Upsert,
+    /// `DELETE`
+    Delete,
+    /// `INSERT`
+    Insert,
+    /// `RELATE`
+    Relate,
+    /// `DEFINE`
+    Define,
+    /// `REMOVE`
+    Remove,
+    /// `ALTER`
+    Alter,
+    /// `INFO`
+    Info,
+    /// `LIVE`
+    Live,
+    /// `KILL`
+    Kill,
+    /// `SHOW`
+    Show,
+    /// `LET`
+    Let,
+    /// `RETURN`
+    Return,
+    /// `IF`
+    If,
+    /// `FOR`
+    For,
+    /// `THROW`
+    Throw,
+    /// `SLEEP`
+    Sleep,
+    /// `USE`
+    Use,
+    /// `BEGIN TRANSACTION`
+    Begin,
+    /// `COMMIT TRANSACTION`
+    Commit,
+    /// `CANCEL TRANSACTION`
+    Cancel,
+    /// A multi-statement script built with `ScriptBuilder`.
+    Script,
+    /// A transaction built with `TransactionBuilder`.
+    Transaction,
+    /// Anything else, including empty input.
+    Other,
+}
+
+impl StatementKind {
+    /// Classifies a raw statement by its leading keyword, ignoring leading
+    /// whitespace and parentheses.
+    pub fn of(stmt: &str) -> Self {
+        let keyword = stmt
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or("")
+            .to_ascii_uppercase();
+        match keyword.as_str() {
+            "SELECT" => StatementKind::Select,
+            "CREATE" => StatementKind::Create,
+            "UPDATE" => StatementKind::Update,
+            "UPSERT" => StatementKind::Upsert,
+            "DELETE" => StatementKind::Delete,
+            "INSERT" => StatementKind::Insert,
+            "RELATE" => StatementKind::Relate,
+            "DEFINE" => StatementKind::Define,
+            "REMOVE" => StatementKind::Remove,
+            "ALTER" => StatementKind::Alter,
+            "INFO" => StatementKind::Info,
+            "LIVE" => StatementKind::Live,
+            "KILL" => StatementKind::Kill,
+            "SHOW" => StatementKind::Show,
+            "LET" => StatementKind::Let,
+            "RETURN" => StatementKind::Return,
+            "IF" => StatementKind::If,
+            "FOR" => StatementKind::For,
+            "THROW" => StatementKind::Throw,
+            "SLEEP" => StatementKind::Sleep,
+            "USE" => StatementKind::Use,
+            "BEGIN" => StatementKind::Begin,
+            "COMMIT" => StatementKind::Commit,
+            "CANCEL" => StatementKind::Cancel,
+            _ => StatementKind::Other,
         }
-        self
     }
 
-    /// Add a `QueryBuilder`'s built query as a statement.
-    pub fn add_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, &'static str> {
-        let q = qb.build()?;
-        Ok(self.add_statement(&q))
+    /// Returns true for statements that never modify data: SELECT, INFO and LIVE.
+    pub fn is_read_only(self) -> bool {
+        matches!(
+            self,
+            StatementKind::Select | StatementKind::Info | StatementKind::Live
+        )
     }
+}
 
-    /// Add a `QueryBuilder`'s built query with a suffix (e.g., `[0].count`).
-    pub fn add_query_with_suffix(
-        &mut self,
-        qb: &QueryBuilder,
-        suffix: &str,
-    ) -> Result<&mut Self, &'static str> {
-        let q = qb.build()?;
-        Ok(self.add_statement(&format!("({}){}", q, suffix)))
-    }
+/// How script builders treat SurrealQL comments in their output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommentMode {
+    /// Keep comments, e.g. for readable migration files.
+    #[default]
+    Preserve,
+    /// Remove comments, e.g. for minified production queries.
+    Strip,
+}
 
-    /// Add an entire `ScriptBuilder` script (it may contain multiple lines).
-    pub fn add_script(&mut self, script: &str) -> &mut Self {
-        // push verbatim; the script may contain its own semicolons and newlines
-        self.statements.push(script.to_string());
-        self
+impl CommentMode {
+    fn apply(self, sql: String) -> String {
+        match self {
+            CommentMode::Preserve => sql,
+            CommentMode::Strip => strip_comments(&sql),
+        }
     }
 
-    /// Add a COMMIT statement. Use this to finalise the transaction.
-    pub fn commit(&mut self) -> &mut Self {
-        self.statements.push("COMMIT TRANSACTION;".to_string());
-        self
+    /// Applies the mode to a single statement, dropping it if nothing but
+    /// comments remain.
+    fn apply_statement(self, stmt: String) -> Option<String> {
+        match self {
+            CommentMode::Preserve => Some(stmt),
+            CommentMode::Strip => Some(strip_comments(&stmt)).filter(|s| !s.is_empty()),
+        }
     }
+}
 
-    /// Add a CANCEL statement. Use this to rollback the transaction.
-    pub fn cancel(&mut self) -> &mut Self {
-        self.statements.push("CANCEL TRANSACTION;".to_string());
-        self
-    }
+/// Renders `text` as `-- ` comment lines, so line breaks in it cannot end
+/// the comment.
+fn render_comment(text: &str) -> String {
+    text.lines()
+        .flat_map(|line| line.split('\r'))
+        .map(|line| format!("-- {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Removes `--`, `#` and `//` line comments and `/* */` block comments from
+/// SurrealQL, leaving quoted strings and escaped identifiers untouched. Lines
+/// left empty by the removal are dropped.
+///
+/// Note that builders append `;` or `)` after raw fragments, so a fragment
+/// ending in a line comment comments those out too; use block comments for
+/// trailing remarks inside fragments.
+pub fn strip_comments(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                out.push(c);
+                while let Some(n) = chars.next() {
+                    out.push(n);
+                    if n == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            out.push(escaped);
+                        }
+                    } else if n == c {
+                        break;
+                    }
+                }
+            }
+            '⟨' => {
+                out.push(c);
+                while let Some(n) = chars.next() {
+                    out.push(n);
+                    if n == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            out.push(escaped);
+                        }
+                    } else if n == '⟩' {
+                        break;
+                    }
+                }
+            }
+            '\n' => end_line(&mut out),
+            '#' => skip_line(&mut chars),
+            '-' if chars.peek() == Some(&'-') => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'/') => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                while out.ends_with([' ', '\t']) {
+                    out.pop();
+                }
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    end_line(&mut out);
+    out.pop();
+    out
+}
+
+/// Trims the trailing whitespace of the current output line and ends it,
+/// unless it is empty. Only called outside literals, so strings keep their
+/// whitespace and blank lines.
+fn end_line(out: &mut String) {
+    while out.ends_with([' ', '\t', '\r']) {
+        out.pop();
+    }
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Byte ranges of the top-level statements in `sql`, each ending with its
+/// `;` (if any) and trimmed of surrounding whitespace. Semicolons inside
+/// quotes, comments and brackets do not split.
+fn split_statements(sql: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut chars = sql.char_indices().peekable();
+    let push = |from: usize, to: usize, ranges: &mut Vec<std::ops::Range<usize>>| {
+        let part = &sql[from..to];
+        let lead = part.len() - part.trim_start().len();
+        if !part.trim().is_empty() {
+            ranges.push(from + lead..from + part.trim_end().len());
+        }
+    };
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                while let Some((_, n)) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == c {
+                        break;
+                    }
+                }
+            }
+            '⟨' => {
+                while let Some((_, n)) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == '⟩' {
+                        break;
+                    }
+                }
+            }
+            '#' => while chars.next_if(|&(_, n)| n != '\n').is_some() {},
+            '-' | '/' if chars.peek().map(|&(_, n)| n) == Some(c) => {
+                while chars.next_if(|&(_, n)| n != '\n').is_some() {}
+            }
+            '/' if chars.peek().map(|&(_, n)| n) == Some('*') => {
+                chars.next();
+                let mut prev = ' ';
+                for (_, n) in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ';' if depth == 0 => {
+                push(start, i + 1, &mut ranges);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    push(start, sql.len(), &mut ranges);
+    ranges
+}
+
+/// Advances past the rest of the current line, leaving the newline.
+fn skip_line(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while let Some(&n) = chars.peek() {
+        if n == '\n' {
+            break;
+        }
+        chars.next();
+    }
+}
+
+/// Builder for SurrealQL transactions.
+///
+/// Usage: create a TransactionBuilder, call `begin()`, add statements (raw strings,
+/// queries from `QueryBuilder`, or full `ScriptBuilder` scripts), then `commit()` or
+/// `cancel()` and `build()` to get the final SurrealQL transaction script.
+#[derive(Debug, Default)]
+pub struct TransactionBuilder {
+    statements: Vec<String>,
+    /// Labels keyed by the index of the statement they describe.
+    labels: BTreeMap<usize, String>,
+    comments: CommentMode,
+    bindings: BTreeMap<String, Value>,
+}
+
+/// A transaction script together with the location of each statement in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedTransaction {
+    /// The script, identical to `TransactionBuilder::build()`.
+    pub script: String,
+    /// One entry per statement, in execution order. Comment lines are omitted.
+    pub statements: Vec<StatementSpan>,
+}
+
+/// Where a statement sits in a built transaction script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementSpan {
+    /// The label given with `TransactionBuilder::label`, if any.
+    pub label: Option<String>,
+    /// The statement kind.
+    pub kind: StatementKind,
+    /// Byte range of the statement within the script.
+    pub span: std::ops::Range<usize>,
+}
+
+impl TransactionBuilder {
+    /// Create a new empty transaction builder.
+    pub fn new() -> Self {
+        Self {
+            statements: Vec::new(),
+            labels: BTreeMap::new(),
+            comments: CommentMode::Preserve,
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Binds a value to the `$name` parameter; a leading `$` is optional.
+    pub fn bind(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        self.bindings
+            .insert(name.trim_start_matches('$').to_string(), value.into());
+        self
+    }
+
+    /// The bound parameters.
+    pub fn bindings(&self) -> &BTreeMap<String, Value> {
+        &self.bindings
+    }
+
+    /// Label the most recently added statement so errors SurrealDB reports
+    /// for it can be traced back via `build_detailed()`.
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        if let Some(last) = self.statements.len().checked_sub(1) {
+            self.labels.insert(last, label.to_string());
+        }
+        self
+    }
+
+    /// Set whether comments (from `comment()` or inside raw statements and
+    /// scripts) are kept or stripped from the output.
+    pub fn comments(&mut self, mode: CommentMode) -> &mut Self {
+        self.comments = mode;
+        self
+    }
+
+    /// Add a `-- text` comment line, one per line of `text`.
+    pub fn comment(&mut self, text: &str) -> &mut Self {
+        self.statements.push(render_comment(text));
+        self
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Transaction
+    }
+
+    /// Start the transaction block. Uses `BEGIN TRANSACTION;`.
+    pub fn begin(&mut self) -> &mut Self {
+        self.statements.push("BEGIN TRANSACTION;".to_string());
+        self
+    }
+
+    /// Add a raw statement (will be terminated with a semicolon if missing).
+    pub fn add_statement(&mut self, stmt: &str) -> &mut Self {
+        self.statements.push(terminate(stmt));
+        self
+    }
+
+    /// Add a `QueryBuilder`'s built query as a statement.
+    pub fn add_query(&mut self, qb: &QueryBuilder) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.add_statement(&q))
+    }
+
+    /// Add a `QueryBuilder`'s built query with a suffix (e.g., `[0].count`).
+    pub fn add_query_with_suffix(
+        &mut self,
+        qb: &QueryBuilder,
+        suffix: impl Into<Accessor>,
+    ) -> Result<&mut Self, BuildError> {
+        let q = qb.build()?;
+        Ok(self.add_statement(&format!("({}){}", q, suffix.into().to_surql())))
+    }
+
+    /// Add an entire `ScriptBuilder` script (it may contain multiple lines).
+    pub fn add_script(&mut self, script: &str) -> &mut Self {
+        // push verbatim; the script may contain its own semicolons and newlines
+        self.statements.push(script.to_string());
+        self
+    }
+
+    /// Add a COMMIT statement. Use this to finalise the transaction.
+    pub fn commit(&mut self) -> &mut Self {
+        self.statements.push("COMMIT TRANSACTION;".to_string());
+        self
+    }
+
+    /// Add a CANCEL statement. Use this to rollback the transaction.
+    pub fn cancel(&mut self) -> &mut Self {
+        self.statements.push("CANCEL TRANSACTION;".to_string());
+        self
+    }
 
     /// Build the final transaction script as a single string.
     pub fn build(&self) -> String {
-        self.statements.join("\n")
+        self.comments.apply(self.statements.join("\n"))
+    }
+
+    /// Build the script along with the label, kind and byte span of every
+    /// statement. Each statement of a script added with `add_script` gets
+    /// its own entry; a label applies to the last one.
+    pub fn build_detailed(&self) -> DetailedTransaction {
+        let mut script = String::new();
+        let mut statements = Vec::new();
+        for (i, st) in self.statements.iter().enumerate() {
+            let Some(rendered) = self.comments.apply_statement(st.clone()) else {
+                continue;
+            };
+            if !script.is_empty() {
+                script.push('\n');
+            }
+            let offset = script.len();
+            script.push_str(&rendered);
+            let parts: Vec<_> = split_statements(&rendered)
+                .into_iter()
+                .filter(|r| !strip_comments(&rendered[r.clone()]).is_empty())
+                .collect();
+            for (n, range) in parts.iter().enumerate() {
+                statements.push(StatementSpan {
+                    label: if n + 1 == parts.len() {
+                        self.labels.get(&i).cloned()
+                    } else {
+                        None
+                    },
+                    kind: StatementKind::of(&strip_comments(&rendered[range.clone()])),
+                    span: offset + range.start..offset + range.end,
+                });
+            }
+        }
+        DetailedTransaction { script, statements }
+    }
+
+    /// Render the transaction one statement at a time; joining the items with
+    /// newlines gives the same output as `build()`.
+    pub fn render_statements(&self) -> impl Iterator<Item = String> + '_ {
+        self.statements
+            .iter()
+            .filter_map(|st| self.comments.apply_statement(st.clone()))
+    }
+
+    /// Wrap the statements in a retry-friendly transaction guarded by an
+    /// idempotency key. The script throws if `idempotency:<key>` already
+    /// exists and otherwise records it alongside the statements, so replaying
+    /// it after an ambiguous failure never applies the changes twice.
+    ///
+    /// Any BEGIN, COMMIT or CANCEL statements already added are replaced by
+    /// the wrapper's own BEGIN and COMMIT.
+    pub fn with_retry(&self, idempotency_key: &str, max_attempts: u32) -> RetryableTransaction {
+        let record = RecordId::new(IDEMPOTENCY_TABLE, idempotency_key).to_surql();
+        let mut out = vec![
+            "BEGIN TRANSACTION;".to_string(),
+            format!(
+                "IF record::exists({}) {{ THROW {}; }};",
+                record,
+                quote_string(&format!("{}{}", ALREADY_APPLIED, idempotency_key))
+            ),
+            format!("CREATE {} SET applied_at = time::now();", record),
+        ];
+        out.extend(
+            self.statements
+                .iter()
+                .filter(|s| {
+                    !matches!(
+                        StatementKind::of(s),
+                        StatementKind::Begin | StatementKind::Commit | StatementKind::Cancel
+                    )
+                })
+                .cloned(),
+        );
+        out.push("COMMIT TRANSACTION;".to_string());
+        RetryableTransaction {
+            script: out.join("\n"),
+            idempotency_key: idempotency_key.to_string(),
+            max_attempts,
+        }
+    }
+}
+
+/// Table holding the idempotency records written by `with_retry`.
+const IDEMPOTENCY_TABLE: &str = "idempotency";
+/// Prefix of the error thrown when an idempotency key was already applied.
+const ALREADY_APPLIED: &str = "idempotency key already applied: ";
+
+/// A transaction script wrapped by [`TransactionBuilder::with_retry`],
+/// together with the metadata an executor needs to retry it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryableTransaction {
+    /// The full transaction script.
+    pub script: String,
+    /// The idempotency key guarding the transaction.
+    pub idempotency_key: String,
+    /// The maximum number of attempts the executor should make.
+    pub max_attempts: u32,
+}
+
+impl RetryableTransaction {
+    /// Returns true if a SurrealDB error message reports a transaction
+    /// conflict, which is safe to retry.
+    pub fn is_retryable_error(&self, message: &str) -> bool {
+        message.contains("read or write conflict") || message.contains("can be retried")
+    }
+
+    /// Returns true if the error was thrown by the idempotency guard, meaning
+    /// an earlier attempt already committed and no retry is needed.
+    pub fn is_already_applied(&self, message: &str) -> bool {
+        message.contains(&format!("{}{}", ALREADY_APPLIED, self.idempotency_key))
+    }
+
+    /// Returns true if another attempt should be made after `attempt`
+    /// (1-based) failed with `message`.
+    pub fn should_retry(&self, attempt: u32, message: &str) -> bool {
+        attempt < self.max_attempts && self.is_retryable_error(message)
     }
 }
 
@@ -402,164 +2253,2738 @@ pub enum Direction {
     Out,
     /// Incoming (`<-`).
     In,
+    /// Either direction (`<->`), for relationships read as undirected.
+    Both,
 }
 
-/// Parameters for a two-step graph traversal expansion.
-#[derive(Debug, Clone)]
-pub struct GraphExpandParams {
-    /// First traversal (direction and graph table).
-    pub from: (Direction, String),
-    /// Second traversal (direction and edge table).
-    pub to: (Direction, String),
-    /// Optional alias for the expansion.
-    pub alias: Option<String>,
+impl Direction {
+    fn arrow(&self) -> &'static str {
+        match self {
+            Direction::Out => "->",
+            Direction::In => "<-",
+            Direction::Both => "<->",
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// A field path (idiom) such as `address.city`, rendered with each segment
+/// escaped. Comparison helpers turn a path into a `Condition`:
+///
+/// ```rust
+/// use surrealex::path;
+///
+/// let cond = path("address").field("city").eq("Berlin");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Path {
+    parts: Vec<PathPart>,
+    /// False for accessor chains created with [`accessor`], which render a
+    /// leading `.` before their first field.
+    rooted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+enum PathPart {
+    Field(String),
+    Index(i64),
+    Last,
+    All,
+    Slice(String),
+    Filter(Condition),
+    Destructure(Vec<String>),
+}
+
+/// References a parameter, e.g. `Condition::eq("age", param("min_age"))`
+/// renders `age = $min_age`. Bind the value with `bind()`.
+pub fn param(name: &str) -> Value {
+    Value::param(name)
+}
+
+/// Starts a `Path` at the given field.
+pub fn path(root: &str) -> Path {
+    Path::new(root)
+}
+
+/// Starts an empty accessor chain to append to another expression, e.g.
+/// `accessor().index(0).field("count")` renders `[0].count`.
+pub fn accessor() -> Path {
+    Path {
+        parts: Vec::new(),
+        rooted: false,
+    }
+}
+
+impl Path {
+    /// Creates a path with a single root field. Unlike `From<&str>`, dots are
+    /// not treated as separators.
+    pub fn new(root: &str) -> Self {
+        Self {
+            parts: vec![PathPart::Field(root.to_string())],
+            rooted: true,
+        }
+    }
+
+    /// Appends a nested field access (`.name`).
+    pub fn field(mut self, name: &str) -> Self {
+        self.parts.push(PathPart::Field(name.to_string()));
+        self
+    }
+
+    /// Appends an array index (`[0]`).
+    pub fn index(mut self, index: i64) -> Self {
+        self.parts.push(PathPart::Index(index));
+        self
+    }
+
+    /// Appends an access to the last array element (`[$]`).
+    pub fn last(mut self) -> Self {
+        self.parts.push(PathPart::Last);
+        self
+    }
+
+    /// Appends an access to every array element (`[*]`).
+    pub fn all(mut self) -> Self {
+        self.parts.push(PathPart::All);
+        self
+    }
+
+    /// Appends an array slice, e.g. `.slice(1..3)` renders `[1..3]` and
+    /// `.slice(..=2)` renders `[..=2]`.
+    pub fn slice(mut self, range: impl RangeBounds<i64>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(n) => n.to_string(),
+            Bound::Excluded(n) => format!("{}>", n),
+            Bound::Unbounded => String::new(),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(n) => format!("={}", n),
+            Bound::Excluded(n) => n.to_string(),
+            Bound::Unbounded => String::new(),
+        };
+        self.parts
+            .push(PathPart::Slice(format!("{}..{}", start, end)));
+        self
+    }
+
+    /// Appends an array filter (`[WHERE ...]`).
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.parts.push(PathPart::Filter(condition));
+        self
+    }
+
+    /// Appends a destructuring of the given fields (`.{city, country}`).
+    pub fn destructure(mut self, fields: &[&str]) -> Self {
+        self.parts.push(PathPart::Destructure(
+            fields.iter().map(|f| f.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Renders the path as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        let mut out = String::new();
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                PathPart::Field(name) => {
+                    if i > 0 || !self.rooted {
+                        out.push('.');
+                    }
+                    out.push_str(&escape_ident(name));
+                }
+                PathPart::Index(n) => out.push_str(&format!("[{}]", n)),
+                PathPart::Last => out.push_str("[$]"),
+                PathPart::All => out.push_str("[*]"),
+                PathPart::Slice(range) => out.push_str(&format!("[{}]", range)),
+                PathPart::Filter(c) => out.push_str(&format!("[WHERE {}]", render_condition(c))),
+                PathPart::Destructure(fields) => {
+                    out.push_str(&format!(".{{{}}}", render_destructure(fields)))
+                }
+            }
+        }
+        out
+    }
+
+    fn compare(&self, op: Operator, value: impl Into<Value>) -> Condition {
+        Condition::Binary(self.clone(), op, value.into())
+    }
+
+    /// `path = value`
+    pub fn eq(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Eq, value)
+    }
+
+    /// `path != value`
+    pub fn ne(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Ne, value)
+    }
+
+    /// `path > value`
+    pub fn gt(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Gt, value)
+    }
+
+    /// `path >= value`
+    pub fn gte(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Gte, value)
+    }
+
+    /// `path < value`
+    pub fn lt(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Lt, value)
+    }
+
+    /// `path <= value`
+    pub fn lte(&self, value: impl Into<Value>) -> Condition {
+        self.compare(Operator::Lte, value)
+    }
+
+    /// `path IS NONE`
+    pub fn is_none(&self) -> Condition {
+        self.compare(Operator::Is, Value::None)
+    }
+
+    /// `path IS NOT NONE`
+    pub fn is_not_none(&self) -> Condition {
+        self.compare(Operator::IsNot, Value::None)
+    }
+
+    /// `path = NULL`
+    pub fn is_null(&self) -> Condition {
+        self.compare(Operator::Eq, Value::Null)
+    }
+
+    /// `path != NULL`
+    pub fn is_not_null(&self) -> Condition {
+        self.compare(Operator::Ne, Value::Null)
+    }
+}
+
+impl From<&str> for Path {
+    /// Parses a dotted path such as `address.city` into its segments.
+    fn from(s: &str) -> Self {
+        Self {
+            parts: s
+                .split('.')
+                .map(|f| PathPart::Field(f.to_string()))
+                .collect(),
+            rooted: true,
+        }
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_surql())
+    }
+}
+
+/// Renders the field list of a destructuring, escaping each field.
+fn render_destructure<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|f| escape_ident(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A suffix appended to a parenthesised expression, such as `[0].count` in
+/// `(SELECT count() FROM t GROUP ALL)[0].count`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Accessor {
+    /// A typed chain built with [`accessor`].
+    Path(Path),
+    /// A verbatim suffix string.
+    Raw(String),
+}
+
+impl Accessor {
+    /// Renders the accessor as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        match self {
+            Accessor::Path(p) => p.to_surql(),
+            Accessor::Raw(s) => s.clone(),
+        }
+    }
+}
+
+impl From<Path> for Accessor {
+    fn from(p: Path) -> Self {
+        Accessor::Path(p)
+    }
+}
+
+impl From<&str> for Accessor {
+    fn from(s: &str) -> Self {
+        Accessor::Raw(s.to_string())
+    }
+}
+
+impl From<String> for Accessor {
+    fn from(s: String) -> Self {
+        Accessor::Raw(s)
+    }
+}
+
+/// Returns true for plain identifiers (`[A-Za-z_][A-Za-z0-9_]*`), usable in
+/// const context.
+const fn is_plain_ident(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes[0].is_ascii_digit() {
+        return false;
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        if !(bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+macro_rules! ident_newtype {
+    ($(#[$doc:meta])* $name:ident, $what:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(std::borrow::Cow<'static, str>);
+
+        impl $name {
+            #[doc = concat!("Creates a ", $what, " name, panicking if it is not a plain identifier.")]
+            /// Evaluated in a `const`, an invalid name is a compile error.
+            pub const fn new_const(name: &'static str) -> Self {
+                assert!(
+                    is_plain_ident(name),
+                    concat!($what, " names must match [A-Za-z_][A-Za-z0-9_]*")
+                );
+                Self(std::borrow::Cow::Borrowed(name))
+            }
+
+            #[doc = concat!("Creates a ", $what, " name from a runtime string.")]
+            pub fn new(name: impl Into<String>) -> Result<Self, BuildError> {
+                let name = name.into();
+                if is_plain_ident(&name) {
+                    Ok(Self(std::borrow::Cow::Owned(name)))
+                } else {
+                    Err(BuildError::new(concat!("Invalid ", $what, " name."))
+                        .with_suggestion("use only ASCII letters, digits and underscores, not starting with a digit"))
+                }
+            }
+
+            /// The name as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::ops::Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+ident_newtype!(
+    /// A validated table name.
+    ///
+    /// ```
+    /// use surrealex::{QueryBuilder, TableName};
+    ///
+    /// const USER: TableName = TableName::new_const("user");
+    /// let sql = QueryBuilder::new().from(USER).build().unwrap();
+    /// assert_eq!(sql, "SELECT * FROM user");
+    /// ```
+    ///
+    /// ```compile_fail
+    /// const BAD: surrealex::TableName = surrealex::TableName::new_const("user-data");
+    /// ```
+    TableName,
+    "table"
+);
+
+ident_newtype!(
+    /// A validated, single-segment field name.
+    FieldName,
+    "field"
+);
+
+impl From<TableName> for Target {
+    fn from(table: TableName) -> Self {
+        Target::Table(escape_ident(&table))
+    }
+}
+
+impl From<&TableName> for Target {
+    fn from(table: &TableName) -> Self {
+        Target::Table(escape_ident(table))
+    }
+}
+
+impl From<FieldName> for Path {
+    fn from(field: FieldName) -> Self {
+        Path::new(&field)
+    }
+}
+
+impl From<&FieldName> for Path {
+    fn from(field: &FieldName) -> Self {
+        Path::new(field)
+    }
+}
+
+/// Keywords that cannot appear as bare identifiers without being read as
+/// part of the statement.
+const RESERVED_WORDS: &[&str] = &[
+    "AND",
+    "AS",
+    "ASC",
+    "BY",
+    "COLLATE",
+    "CONTAINS",
+    "CONTAINSALL",
+    "CONTAINSANY",
+    "CONTAINSNONE",
+    "CONTAINSNOT",
+    "DESC",
+    "EXPLAIN",
+    "FALSE",
+    "FETCH",
+    "FROM",
+    "GROUP",
+    "IN",
+    "INSIDE",
+    "INTERSECTS",
+    "IS",
+    "LIMIT",
+    "NONE",
+    "NOT",
+    "NOTINSIDE",
+    "NULL",
+    "NUMERIC",
+    "OMIT",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "OUTSIDE",
+    "PARALLEL",
+    "SELECT",
+    "SPLIT",
+    "START",
+    "TEMPFILES",
+    "TIMEOUT",
+    "TRUE",
+    "WHERE",
+    "WITH",
+];
+
+/// Verbatim SurrealQL, the explicit escape hatch for what the typed API cannot
+/// express. It is never escaped, so it must never contain untrusted input.
+///
+/// Accepted as a condition, a value (e.g. `time::now() - 1d`) and a FROM
+/// target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Raw(pub String);
+
+impl Raw {
+    /// Wraps trusted SurrealQL.
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self(sql.into())
+    }
+}
+
+impl From<Raw> for Condition {
+    fn from(raw: Raw) -> Self {
+        Condition::Raw(raw.0)
+    }
+}
+
+impl From<Raw> for Value {
+    fn from(raw: Raw) -> Self {
+        Value::Raw(raw.0)
+    }
+}
+
+impl From<Raw> for Target {
+    fn from(raw: Raw) -> Self {
+        Target::Table(raw.0)
+    }
+}
+
+/// An identifier (table, field or alias name) rendered bare when safe and
+/// wrapped in backticks when it contains other characters or is a reserved
+/// word, e.g. `user-profile` or `group`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ident(String);
+
+impl Ident {
+    /// Wraps a name; escaping happens when it is rendered.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// The unescaped name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Renders the identifier, escaped if necessary.
+    pub fn to_surql(&self) -> String {
+        escape_ident(&self.0)
+    }
+}
+
+impl std::fmt::Display for Ident {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_surql())
+    }
+}
+
+impl From<&str> for Ident {
+    fn from(name: &str) -> Self {
+        Ident::new(name)
+    }
+}
+
+impl From<String> for Ident {
+    fn from(name: String) -> Self {
+        Ident::new(name)
+    }
+}
+
+impl From<Ident> for Target {
+    fn from(table: Ident) -> Self {
+        Target::Table(table.to_surql())
+    }
+}
+
+impl From<Ident> for Path {
+    fn from(field: Ident) -> Self {
+        Path::new(&field.0)
+    }
+}
+
+/// Renders an identifier bare when it is a plain identifier and wrapped in
+/// backticks otherwise, including for reserved words.
+fn escape_ident(name: &str) -> String {
+    let plain =
+        is_plain_ident(name) && !RESERVED_WORDS.iter().any(|w| w.eq_ignore_ascii_case(name));
+    if plain {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+    }
+}
+
+/// Distance metric for vector search and vector indexes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Distance {
+    /// `CHEBYSHEV`
+    Chebyshev,
+    /// `COSINE`
+    Cosine,
+    /// `EUCLIDEAN`
+    Euclidean,
+    /// `HAMMING`
+    Hamming,
+    /// `JACCARD`
+    Jaccard,
+    /// `MANHATTAN`
+    Manhattan,
+    /// `MINKOWSKI <order>`
+    Minkowski(u32),
+    /// `PEARSON`
+    Pearson,
+}
+
+impl Distance {
+    fn as_surql(self) -> String {
+        match self {
+            Distance::Chebyshev => "CHEBYSHEV".to_string(),
+            Distance::Cosine => "COSINE".to_string(),
+            Distance::Euclidean => "EUCLIDEAN".to_string(),
+            Distance::Hamming => "HAMMING".to_string(),
+            Distance::Jaccard => "JACCARD".to_string(),
+            Distance::Manhattan => "MANHATTAN".to_string(),
+            Distance::Minkowski(order) => format!("MINKOWSKI {}", order),
+            Distance::Pearson => "PEARSON".to_string(),
+        }
+    }
+}
+
+/// The optional second parameter of the KNN operator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum KnnParam {
+    /// The HNSW search size (`<|k,EF|>`).
+    Ef(u32),
+    /// A distance metric for a brute-force search (`<|k,DIST|>`).
+    Distance(Distance),
+}
+
+/// A comparison operator for [`Condition::binary`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Operator {
+    /// `=`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `==`, equality without type coercion.
+    ExactEq,
+    /// `>`
+    Gt,
+    /// `>=`
+    Gte,
+    /// `<`
+    Lt,
+    /// `<=`
+    Lte,
+    /// `IS`
+    Is,
+    /// `IS NOT`
+    IsNot,
+    /// `~`
+    Fuzzy,
+    /// `!~`
+    NotFuzzy,
+    /// `?~`
+    AnyFuzzy,
+    /// `*~`
+    AllFuzzy,
+    /// `@ref@`, a full-text search match.
+    Matches(u32),
+    /// `<|k|>`, `<|k,EF|>` or `<|k,DIST|>`, a nearest-neighbour search.
+    Knn(u32, Option<KnnParam>),
+    /// `CONTAINS`
+    Contains,
+    /// `CONTAINSNOT`
+    ContainsNot,
+    /// `CONTAINSALL`
+    ContainsAll,
+    /// `CONTAINSANY`
+    ContainsAny,
+    /// `CONTAINSNONE`
+    ContainsNone,
+    /// `IN`
+    In,
+    /// `INSIDE`
+    Inside,
+    /// `NOTINSIDE`
+    NotInside,
+    /// `ALLINSIDE`
+    AllInside,
+    /// `ANYINSIDE`
+    AnyInside,
+    /// `NONEINSIDE`
+    NoneInside,
+    /// `OUTSIDE`
+    Outside,
+    /// `INTERSECTS`
+    Intersects,
+}
+
+impl Operator {
+    fn as_surql(self) -> String {
+        match self {
+            Operator::Eq => "=".to_string(),
+            Operator::Ne => "!=".to_string(),
+            Operator::ExactEq => "==".to_string(),
+            Operator::Gt => ">".to_string(),
+            Operator::Gte => ">=".to_string(),
+            Operator::Lt => "<".to_string(),
+            Operator::Lte => "<=".to_string(),
+            Operator::Is => "IS".to_string(),
+            Operator::IsNot => "IS NOT".to_string(),
+            Operator::Fuzzy => "~".to_string(),
+            Operator::NotFuzzy => "!~".to_string(),
+            Operator::AnyFuzzy => "?~".to_string(),
+            Operator::AllFuzzy => "*~".to_string(),
+            Operator::Matches(reference) => format!("@{}@", reference),
+            Operator::Knn(k, None) => format!("<|{}|>", k),
+            Operator::Knn(k, Some(KnnParam::Ef(ef))) => format!("<|{},{}|>", k, ef),
+            Operator::Knn(k, Some(KnnParam::Distance(d))) => format!("<|{},{}|>", k, d.as_surql()),
+            Operator::Contains => "CONTAINS".to_string(),
+            Operator::ContainsNot => "CONTAINSNOT".to_string(),
+            Operator::ContainsAll => "CONTAINSALL".to_string(),
+            Operator::ContainsAny => "CONTAINSANY".to_string(),
+            Operator::ContainsNone => "CONTAINSNONE".to_string(),
+            Operator::In => "IN".to_string(),
+            Operator::Inside => "INSIDE".to_string(),
+            Operator::NotInside => "NOTINSIDE".to_string(),
+            Operator::AllInside => "ALLINSIDE".to_string(),
+            Operator::AnyInside => "ANYINSIDE".to_string(),
+            Operator::NoneInside => "NONEINSIDE".to_string(),
+            Operator::Outside => "OUTSIDE".to_string(),
+            Operator::Intersects => "INTERSECTS".to_string(),
+        }
+    }
+}
+
+/// Sort direction for ORDER BY entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Order {
+    /// Ascending (`ASC`).
+    Asc,
+    /// Descending (`DESC`).
+    Desc,
+}
+
+impl Order {
+    fn as_str(self) -> &'static str {
+        match self {
+            Order::Asc => "ASC",
+            Order::Desc => "DESC",
+        }
+    }
+}
+
+/// Parameters for a two-step graph traversal expansion.
+#[derive(Debug, Clone)]
+pub struct GraphExpandParams {
+    /// First traversal (direction and graph table).
+    pub from: (Direction, String),
+    /// Second traversal (direction and edge table).
+    pub to: (Direction, String),
+    /// Field selected from the records reached: `*` renders `.*`, a field
+    /// name renders `.name`, and `None` selects the records themselves.
+    pub projection: Option<String>,
+    /// Optional alias for the expansion.
+    pub alias: Option<String>,
+}
+
+/// One row of the output of an `EXPLAIN` query.
+///
+/// Deserialize the result of a query built with [`QueryBuilder::explain`] into a
+/// `Vec<ExplainReport>` to assert on the plan SurrealDB chose.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct ExplainReport {
+    /// The plan step, e.g. `Iterate Index`, `Iterate Table` or `Collector`.
+    pub operation: String,
+    /// Step-specific details.
+    #[serde(default)]
+    pub detail: ExplainDetail,
+}
+
+/// The `detail` object of an [`ExplainReport`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+pub struct ExplainDetail {
+    /// The table being iterated, if any.
+    pub table: Option<String>,
+    /// The index plan, present for `Iterate Index` steps.
+    pub plan: Option<ExplainPlan>,
+    /// The number of fetched records (EXPLAIN FULL only).
+    pub count: Option<u64>,
+}
+
+/// The index plan of an `Iterate Index` step.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+pub struct ExplainPlan {
+    /// The name of the index used.
+    pub index: Option<String>,
+    /// The operator evaluated against the index.
+    pub operator: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl ExplainReport {
+    /// The index used by this step, if it is an index iteration.
+    pub fn index(&self) -> Option<&str> {
+        self.detail.plan.as_ref()?.index.as_deref()
+    }
+
+    /// Returns true if any step of the plan iterates the given index.
+    pub fn uses_index(reports: &[ExplainReport], index: &str) -> bool {
+        reports.iter().any(|r| r.index() == Some(index))
+    }
+}
+
+/// A SurrealQL literal value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Value {
+    /// The absence of a value, rendered as `NONE`.
+    None,
+    /// An explicit null, rendered as `NULL`.
+    Null,
+    /// A string, rendered single-quoted with quotes and backslashes escaped.
+    String(String),
+    /// An integer.
+    Int(i64),
+    /// A floating point number; infinities and NaN render as `math::inf`,
+    /// `math::neg_inf` and `math::nan`.
+    Float(f64),
+    /// A boolean.
+    Bool(bool),
+    /// An ISO-8601 datetime, rendered as `d'...'`.
+    Datetime(String),
+    /// A duration, rendered as a duration literal such as `1h30m`.
+    Duration(Duration),
+    /// An array of values.
+    Array(Vec<Value>),
+    /// A record id, e.g. `user:123`.
+    RecordId(RecordId),
+    /// A geometry, rendered as a point literal or GeoJSON-style object.
+    Geometry(Geometry),
+    /// A UUID, rendered as `u'...'`.
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
+    /// An exact decimal, rendered with the `dec` suffix, e.g. `19.99dec`.
+    #[cfg(feature = "rust_decimal")]
+    Decimal(rust_decimal::Decimal),
+    /// A reference to a bound parameter, rendered as `$name`.
+    Param(String),
+    /// A verbatim expression such as `time::now() - 1d`, created from [`Raw`].
+    Raw(String),
+    /// An object. Keys render in the stored order, which is insertion order
+    /// for ordered inputs and sorted order for `HashMap`s.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Creates a datetime literal from an ISO-8601 string.
+    pub fn datetime(s: &str) -> Self {
+        Value::Datetime(s.to_string())
+    }
+
+    /// Creates a `$name` parameter reference; a leading `$` is optional.
+    pub fn param(name: &str) -> Self {
+        Value::Param(name.trim_start_matches('$').to_string())
+    }
+
+    /// Renders the value as a SurrealQL literal.
+    pub fn to_surql(&self) -> String {
+        match self {
+            Value::None => "NONE".to_string(),
+            Value::Null => "NULL".to_string(),
+            Value::String(s) => quote_string(s),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) if f.is_nan() => "math::nan".to_string(),
+            Value::Float(f) if f.is_infinite() && *f > 0.0 => "math::inf".to_string(),
+            Value::Float(f) if f.is_infinite() => "math::neg_inf".to_string(),
+            Value::Float(f) => format!("{:?}", f),
+            Value::Bool(b) => b.to_string(),
+            Value::Datetime(d) => format!("d{}", quote_string(d)),
+            Value::Duration(d) => render_duration(*d),
+            Value::Param(name) => format!("${}", name),
+            Value::Raw(expr) => expr.clone(),
+            #[cfg(feature = "uuid")]
+            Value::Uuid(id) => format!("u'{}'", id.hyphenated()),
+            #[cfg(feature = "rust_decimal")]
+            Value::Decimal(d) => format!("{}dec", d),
+            Value::Array(items) => {
+                let rendered: Vec<String> = items.iter().map(Value::to_surql).collect();
+                format!("[{}]", rendered.join(", "))
+            }
+            Value::RecordId(id) => id.to_surql(),
+            Value::Geometry(g) => g.to_surql(),
+            Value::Object(fields) if fields.is_empty() => "{}".to_string(),
+            Value::Object(fields) => {
+                let rendered: Vec<String> = fields
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", render_object_key(k), v.to_surql()))
+                    .collect();
+                format!("{{ {} }}", rendered.join(", "))
+            }
+        }
+    }
+}
+
+/// Renders a duration using the largest SurrealQL units first, e.g. `1d2h`.
+fn render_duration(duration: Duration) -> String {
+    const UNITS: [(&str, u128); 8] = [
+        ("w", 604_800_000_000_000),
+        ("d", 86_400_000_000_000),
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+    let mut remaining = duration.as_nanos();
+    if remaining == 0 {
+        return "0ns".to_string();
+    }
+    let mut out = String::new();
+    for (unit, nanos) in UNITS {
+        if remaining >= nanos {
+            out.push_str(&format!("{}{}", remaining / nanos, unit));
+            remaining %= nanos;
+        }
+    }
+    out
+}
+
+/// Renders an object key bare when it is a plain identifier and quoted
+/// otherwise.
+fn render_object_key(key: &str) -> String {
+    if is_plain_ident(key) {
+        key.to_string()
+    } else {
+        quote_string(key)
+    }
+}
+
+/// Wraps a string in single quotes, escaping backslashes, single quotes and
+/// control characters so that untrusted input can never end the literal.
+fn quote_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Int(i as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<RecordId> for Value {
+    fn from(id: RecordId) -> Self {
+        Value::RecordId(id)
+    }
+}
+
+impl From<Geometry> for Value {
+    fn from(g: Geometry) -> Self {
+        Value::Geometry(g)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Value::Object(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<HashMap<K, V>> for Value {
+    /// Keys are sorted so the rendered object is stable across runs.
+    fn from(map: HashMap<K, V>) -> Self {
+        let mut fields: Vec<(String, Value)> =
+            map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+        Value::Object(fields)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Value
+where
+    Tz::Offset: std::fmt::Display,
+{
+    fn from(dt: chrono::DateTime<Tz>) -> Self {
+        Value::Datetime(dt.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Value {
+    /// Formatted by hand as RFC 3339, since the `time` formatter rejects
+    /// years before 0; those get a sign, as with chrono.
+    fn from(dt: time::OffsetDateTime) -> Self {
+        let year = dt.year();
+        let year = if (0..=9999).contains(&year) {
+            format!("{:04}", year)
+        } else {
+            format!("{:+05}", year)
+        };
+        let nanos = dt.nanosecond();
+        let fraction = if nanos == 0 {
+            String::new()
+        } else {
+            format!(".{:09}", nanos).trim_end_matches('0').to_string()
+        };
+        let offset = dt.offset();
+        let offset = if offset.is_utc() {
+            "Z".to_string()
+        } else {
+            let (h, m, _) = offset.as_hms();
+            let sign = if offset.is_negative() { '-' } else { '+' };
+            format!("{}{:02}:{:02}", sign, h.unsigned_abs(), m.unsigned_abs())
+        };
+        Value::Datetime(format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}{}{}",
+            year,
+            u8::from(dt.month()),
+            dt.day(),
+            dt.hour(),
+            dt.minute(),
+            dt.second(),
+            fraction,
+            offset
+        ))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Value {
+    fn from(id: uuid::Uuid) -> Self {
+        Value::Uuid(id)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Value {
+    fn from(d: rust_decimal::Decimal) -> Self {
+        Value::Decimal(d)
+    }
+}
+
+impl From<Duration> for Value {
+    fn from(d: Duration) -> Self {
+        Value::Duration(d)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    /// `None` renders as `NONE`.
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::None, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A geometry value for geospatial conditions. Coordinates are
+/// `(longitude, latitude)` pairs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Geometry {
+    /// A single point, rendered as `(lon, lat)`.
+    Point(f64, f64),
+    /// A line through two or more points, rendered as
+    /// `{ type: 'LineString', coordinates: [...] }`.
+    LineString(Vec<(f64, f64)>),
+    /// A polygon made of an exterior ring followed by optional holes, rendered
+    /// as `{ type: 'Polygon', coordinates: [...] }`.
+    Polygon(Vec<Vec<(f64, f64)>>),
+    /// Several polygons, each given as its rings, rendered as
+    /// `{ type: 'MultiPolygon', coordinates: [...] }`.
+    MultiPolygon(Vec<Vec<Vec<(f64, f64)>>>),
+}
+
+impl Geometry {
+    /// Renders the geometry as SurrealQL.
+    pub fn to_surql(&self) -> String {
+        match self {
+            Geometry::Point(x, y) => format!("({:?}, {:?})", x, y),
+            Geometry::LineString(coords) => {
+                format!(
+                    "{{ type: 'LineString', coordinates: {} }}",
+                    render_coords(coords)
+                )
+            }
+            Geometry::Polygon(rings) => {
+                format!(
+                    "{{ type: 'Polygon', coordinates: {} }}",
+                    render_rings(rings)
+                )
+            }
+            Geometry::MultiPolygon(polygons) => {
+                let polygons: Vec<String> = polygons.iter().map(|p| render_rings(p)).collect();
+                format!(
+                    "{{ type: 'MultiPolygon', coordinates: [{}] }}",
+                    polygons.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Renders polygon rings as `[[[x, y], ...], ...]`.
+fn render_rings(rings: &[Vec<(f64, f64)>]) -> String {
+    let rings: Vec<String> = rings.iter().map(|r| render_coords(r)).collect();
+    format!("[{}]", rings.join(", "))
+}
+
+/// A `(longitude, latitude)` point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point(pub f64, pub f64);
+
+/// A line through a sequence of points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineString(pub Vec<Point>);
+
+/// A polygon with an exterior ring and optional holes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    /// The outer boundary.
+    pub exterior: LineString,
+    /// Holes cut out of the exterior.
+    pub interiors: Vec<LineString>,
+}
+
+/// A collection of polygons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiPolygon(pub Vec<Polygon>);
+
+impl Polygon {
+    /// Creates a polygon without holes.
+    pub fn new(exterior: LineString) -> Self {
+        Self {
+            exterior,
+            interiors: Vec::new(),
+        }
+    }
+
+    fn rings(&self) -> Vec<Vec<(f64, f64)>> {
+        std::iter::once(&self.exterior)
+            .chain(&self.interiors)
+            .map(LineString::coords)
+            .collect()
+    }
+}
+
+impl LineString {
+    fn coords(&self) -> Vec<(f64, f64)> {
+        self.0.iter().map(|p| (p.0, p.1)).collect()
+    }
+}
+
+impl From<Point> for Geometry {
+    fn from(p: Point) -> Self {
+        Geometry::Point(p.0, p.1)
+    }
+}
+
+impl From<LineString> for Geometry {
+    fn from(line: LineString) -> Self {
+        Geometry::LineString(line.coords())
+    }
+}
+
+impl From<Polygon> for Geometry {
+    fn from(polygon: Polygon) -> Self {
+        Geometry::Polygon(polygon.rings())
+    }
+}
+
+impl From<MultiPolygon> for Geometry {
+    fn from(multi: MultiPolygon) -> Self {
+        Geometry::MultiPolygon(multi.0.iter().map(Polygon::rings).collect())
+    }
+}
+
+macro_rules! geometry_into_value {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for Value {
+            fn from(g: $ty) -> Self {
+                Value::Geometry(g.into())
+            }
+        })*
+    };
+}
+
+geometry_into_value!(Point, LineString, Polygon, MultiPolygon);
+
+#[cfg(feature = "geo-types")]
+impl From<geo_types::Point<f64>> for Point {
+    fn from(p: geo_types::Point<f64>) -> Self {
+        Point(p.x(), p.y())
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<geo_types::LineString<f64>> for LineString {
+    fn from(line: geo_types::LineString<f64>) -> Self {
+        LineString(line.points().map(Point::from).collect())
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<geo_types::Polygon<f64>> for Polygon {
+    fn from(polygon: geo_types::Polygon<f64>) -> Self {
+        let (exterior, interiors) = polygon.into_inner();
+        Polygon {
+            exterior: exterior.into(),
+            interiors: interiors.into_iter().map(LineString::from).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<geo_types::MultiPolygon<f64>> for MultiPolygon {
+    fn from(multi: geo_types::MultiPolygon<f64>) -> Self {
+        MultiPolygon(multi.into_iter().map(Polygon::from).collect())
+    }
+}
+
+/// Renders a list of coordinates as `[[x, y], ...]`.
+fn render_coords(coords: &[(f64, f64)]) -> String {
+    let coords: Vec<String> = coords
+        .iter()
+        .map(|(x, y)| format!("[{:?}, {:?}]", x, y))
+        .collect();
+    format!("[{}]", coords.join(", "))
+}
+
+/// The id part of a record id (the `1` in `person:1`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum RecordIdKey {
+    /// A numeric id, e.g. `person:1`.
+    Number(i64),
+    /// A string id, rendered bare when it is a plain identifier and wrapped in
+    /// `⟨ ⟩` otherwise.
+    String(String),
+    /// An array id, e.g. `temperature:['London', d'2024-01-01']`.
+    Array(Vec<Value>),
+    /// An object id, e.g. `reading:{ city: 'London', day: 1 }`. Keys render
+    /// in the stored order.
+    Object(Vec<(String, Value)>),
+    /// A UUID id, e.g. `session:u'0190d7a1-...'`.
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
+}
+
+impl RecordIdKey {
+    /// Renders the id part as it appears after the `:` of a record id.
+    pub fn to_surql(&self) -> String {
+        match self {
+            RecordIdKey::Number(n) => n.to_string(),
+            RecordIdKey::String(s) => escape_record_part(s),
+            RecordIdKey::Array(items) => Value::Array(items.clone()).to_surql(),
+            RecordIdKey::Object(fields) => Value::Object(fields.clone()).to_surql(),
+            #[cfg(feature = "uuid")]
+            RecordIdKey::Uuid(id) => Value::Uuid(*id).to_surql(),
+        }
+    }
+}
+
+/// Renders a table name or string id bare when it is a plain identifier, and
+/// wrapped in `⟨ ⟩` otherwise (including all-digit strings, which would
+/// otherwise be read back as numbers).
+fn escape_record_part(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !s.chars().all(|c| c.is_ascii_digit());
+    if plain {
+        s.to_string()
+    } else {
+        format!("⟨{}⟩", s.replace('\\', "\\\\").replace('⟩', "\\⟩"))
+    }
+}
+
+impl From<i64> for RecordIdKey {
+    fn from(n: i64) -> Self {
+        RecordIdKey::Number(n)
+    }
+}
+
+impl From<i32> for RecordIdKey {
+    fn from(n: i32) -> Self {
+        RecordIdKey::Number(n as i64)
+    }
+}
+
+impl From<&str> for RecordIdKey {
+    fn from(s: &str) -> Self {
+        RecordIdKey::String(s.to_string())
+    }
+}
+
+impl From<String> for RecordIdKey {
+    fn from(s: String) -> Self {
+        RecordIdKey::String(s)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for RecordIdKey {
+    fn from(id: uuid::Uuid) -> Self {
+        RecordIdKey::Uuid(id)
+    }
+}
+
+impl From<Vec<Value>> for RecordIdKey {
+    fn from(items: Vec<Value>) -> Self {
+        RecordIdKey::Array(items)
+    }
+}
+
+impl From<Vec<(String, Value)>> for RecordIdKey {
+    fn from(fields: Vec<(String, Value)>) -> Self {
+        RecordIdKey::Object(fields)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for RecordIdKey {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        RecordIdKey::Object(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// A typed record id (`table:id`), rendered with the correct escaping for
+/// both parts instead of being concatenated by hand.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RecordId {
+    /// The table the record belongs to.
+    pub table: String,
+    /// The id within the table.
+    pub id: RecordIdKey,
+}
+
+impl RecordId {
+    /// Creates a record id from a table name and an id.
+    pub fn new(table: &str, id: impl Into<RecordIdKey>) -> Self {
+        Self {
+            table: table.to_string(),
+            id: id.into(),
+        }
+    }
+
+    /// Renders the record id, e.g. `user:123` or `user:⟨john@doe.com⟩`.
+    pub fn to_surql(&self) -> String {
+        format!("{}:{}", escape_record_part(&self.table), self.id.to_surql())
+    }
+}
+
+impl<K: Into<RecordIdKey>> From<(&str, K)> for RecordId {
+    /// `("user", 1).into()` is `user:1`.
+    fn from((table, id): (&str, K)) -> Self {
+        RecordId::new(table, id)
+    }
+}
+
+impl From<&RecordId> for Value {
+    fn from(id: &RecordId) -> Self {
+        Value::RecordId(id.clone())
+    }
+}
+
+impl std::fmt::Display for RecordId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_surql())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_select_from() {
+        let sql = QueryBuilder::new().from("user").build().unwrap();
+        assert_eq!(sql, "SELECT * FROM user");
+    }
+
+    #[test]
+    fn select_where_order_limit_start() {
+        let sql = QueryBuilder::new()
+            .select("id, name", None)
+            .from("user")
+            .where_simple("active = true")
+            .order_by("name ASC")
+            .limit(5)
+            .start(10)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, name FROM user WHERE active = true ORDER BY name ASC LIMIT 5 START 10"
+        );
+    }
+
+    #[test]
+    fn fetch_and_graph_expand() {
+        let sql = QueryBuilder::new()
+            .from("post")
+            .fetch("comments")
+            .graph_expand("likes")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT *, likes FROM post FETCH comments");
+    }
+
+    #[test]
+    fn complex_where_conditions() {
+        let cond = Condition::And(vec![
+            Condition::Simple("a = 1".into()),
+            Condition::Or(vec![
+                Condition::Simple("b = 2".into()),
+                Condition::Simple("c = 3".into()),
+            ]),
+        ]);
+        let sql = QueryBuilder::new()
+            .from("t")
+            .where_complex(cond)
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM t WHERE (a = 1 AND (b = 2 OR c = 3))");
+    }
+
+    #[test]
+    fn duplicate_simple_conditions_render_once() {
+        let sql = QueryBuilder::new()
+            .from("post")
+            .where_simple("tenant = $t")
+            .where_simple("published = true")
+            .where_simple("tenant  =   $t ")
+            .where_complex(Condition::Simple("tenant = $t".into()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM post WHERE tenant = $t AND published = true"
+        );
+    }
+
+    #[test]
+    fn duplicate_conditions_inside_groups() {
+        let sql = QueryBuilder::new()
+            .from("t")
+            .where_complex(Condition::Or(vec![
+                Condition::Simple("a = 1".into()),
+                Condition::Simple("a = 1".into()),
+                Condition::Simple("b = 2".into()),
+            ]))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM t WHERE (a = 1 OR b = 2)");
+    }
+
+    #[test]
+    fn condition_parameters() {
+        let cond = Condition::And(vec![
+            Condition::Simple("tenant = $tenant".into()),
+            Condition::Or(vec![
+                Condition::Simple("owner = $auth.id".into()),
+                Condition::Simple("shared_with CONTAINS $user_id".into()),
+            ]),
+            Condition::Simple("note != '$not_a_param' AND id IN $parent.refs".into()),
+            Condition::Simple("id = t:⟨a\\⟩$b⟩".into()),
+        ]);
+        let params: Vec<String> = cond.parameters().into_iter().collect();
+        assert_eq!(params, vec!["tenant", "user_id"]);
+
+        let params: Vec<String> = Condition::is_in("a", vec![param("x")])
+            .parameters()
+            .into_iter()
+            .collect();
+        assert_eq!(params, vec!["x"]);
+    }
+
+    #[test]
+    fn fragment_registry_reuse() {
+        let mut registry = FragmentRegistry::new();
+        registry.register(
+            "visible_to_user",
+            Condition::Or(vec![
+                Condition::Simple("public = true".into()),
+                Condition::Simple("owner = $user".into()),
+            ]),
+        );
+
+        let sql = QueryBuilder::new()
+            .from("post")
+            .where_fragment(&registry, "visible_to_user")
+            .unwrap()
+            .where_simple("draft = false")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM post WHERE (public = true OR owner = $user) AND draft = false"
+        );
+        assert_eq!(registry.parameters("visible_to_user").unwrap().len(), 1);
+
+        let err = QueryBuilder::new()
+            .from("post")
+            .where_fragment(&registry, "missing")
+            .unwrap_err();
+        assert_eq!(err, "Unknown condition fragment.");
+    }
+
+    #[test]
+    fn complexity_heuristic() {
+        let cheap = QueryBuilder::new()
+            .from(RecordId::new("user", 1))
+            .complexity();
+        assert_eq!(cheap, 1);
+
+        let scan = QueryBuilder::new().from("user").complexity();
+        assert_eq!(scan, 11);
+        let bounded = QueryBuilder::new().from("user").limit(10).complexity();
+        assert_eq!(bounded, 1);
+
+        let heavy = QueryBuilder::new()
+            .from("user")
+            .where_simple("active = true")
+            .graph_expand("->friends->user->likes->post AS liked")
+            .fetch("org")
+            .complexity();
+        assert_eq!(heavy, 1 + 2 * 4 + 3);
+
+        let directed = QueryBuilder::new()
+            .from("user")
+            .limit(5)
+            .graph_expand("->knows->person")
+            .complexity();
+        let undirected = QueryBuilder::new()
+            .from("user")
+            .limit(5)
+            .graph_expand("<->knows<->person")
+            .complexity();
+        assert_eq!(undirected, directed);
+        assert_eq!(undirected, 1 + 2 * 2);
+
+        let nested = QueryBuilder::new()
+            .from(QueryBuilder::new().from("user"))
+            .limit(5)
+            .complexity();
+        assert_eq!(nested, 1 + 11);
+    }
+
+    #[test]
+    fn typed_operator_constructors() {
+        let cond = Condition::And(vec![
+            Condition::eq("name", "O'Brien"),
+            Condition::ne("status", "archived"),
+            Condition::gt("price", 9.5),
+            Condition::gte("stock", 1),
+            Condition::lt("address.zip", 20000),
+            Condition::lte("active", true),
+        ]);
+        let sql = QueryBuilder::new()
+            .from("product")
+            .where_complex(cond)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM product WHERE (name = 'O\\'Brien' AND status != 'archived' AND price > 9.5 \
+             AND stock >= 1 AND address.zip < 20000 AND active <= true)"
+        );
+        assert_eq!(
+            Condition::eq(path("tags").index(0), "new"),
+            path("tags").index(0).eq("new")
+        );
+    }
+
+    #[test]
+    fn negated_conditions() {
+        let sql = QueryBuilder::new()
+            .from("user")
+            .where_complex(Condition::eq("banned", true).negate())
+            .where_complex(Condition::Not(Box::new(Condition::Or(vec![
+                Condition::Simple("a = 1".into()),
+                Condition::Simple("b = $b".into()),
+            ]))))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM user WHERE !(banned = true) AND !(a = 1 OR b = $b)"
+        );
+
+        let cond = Condition::Simple("x".into());
+        assert_eq!(cond.clone().negate().negate(), cond);
+    }
+
+    #[test]
+    fn where_in_lists() {
+        let sql = QueryBuilder::new()
+            .from("ticket")
+            .where_in("status", ["open", "it's pending"])
+            .where_in("priority", [1, 2])
+            .where_complex(Condition::is_in(
+                "owner",
+                [RecordId::new("user", 1), RecordId::new("user", "a b")],
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM ticket WHERE status IN ['open', 'it\\'s pending'] \
+             AND priority IN [1, 2] AND owner IN [user:1, user:⟨a b⟩]"
+        );
+    }
+
+    #[test]
+    fn none_and_null_helpers() {
+        let sql = QueryBuilder::new()
+            .from("user")
+            .where_complex(Condition::is_none("deleted_at"))
+            .where_complex(Condition::is_not_none("email"))
+            .where_complex(Condition::is_null("profile.bio"))
+            .where_complex(path("nickname").is_not_null())
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM user WHERE deleted_at IS NONE AND email IS NOT NONE \
+             AND profile.bio = NULL AND nickname != NULL"
+        );
+    }
+
+    #[test]
+    fn fuzzy_match_helpers() {
+        let cond = Condition::Or(vec![
+            Condition::fuzzy("name", "jon"),
+            Condition::not_fuzzy("name", "bot"),
+            Condition::any_fuzzy("aliases", "j'o"),
+            Condition::all_fuzzy("tags", "rust"),
+        ]);
+        let sql = QueryBuilder::new()
+            .from("user")
+            .where_complex(cond)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM user WHERE (name ~ 'jon' OR name !~ 'bot' \
+             OR aliases ?~ 'j\\'o' OR tags *~ 'rust')"
+        );
+    }
+
+    #[test]
+    fn full_text_search() {
+        let sql = QueryBuilder::new()
+            .select("id", None)
+            .select_search_highlight("<b>", "</b>", 1, Some("title"))
+            .select_search_score(1, Some("score"))
+            .from("book")
+            .where_complex(Condition::matches("title", 1, "rust's borrow"))
+            .order_by("score DESC")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, search::highlight('<b>', '</b>', 1) AS title, search::score(1) AS score \
+             FROM book WHERE title @1@ 'rust\\'s borrow' ORDER BY score DESC"
+        );
+    }
+
+    #[test]
+    fn knn_operators() {
+        let v = [0.1, -2.0, 3.5];
+        assert_eq!(
+            render_condition(&Condition::knn("embedding", 5, None, &v)),
+            "embedding <|5|> [0.1, -2.0, 3.5]"
+        );
+        assert_eq!(
+            render_condition(&Condition::knn("embedding", 5, Some(KnnParam::Ef(40)), &v)),
+            "embedding <|5,40|> [0.1, -2.0, 3.5]"
+        );
+        let sql = QueryBuilder::new()
+            .select("id", None)
+            .select("vector::distance::knn()", Some("dist"))
+            .from("doc")
+            .where_complex(Condition::knn(
+                "embedding",
+                3,
+                Some(KnnParam::Distance(Distance::Cosine)),
+                &v,
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, vector::distance::knn() AS dist FROM doc \
+             WHERE embedding <|3,COSINE|> [0.1, -2.0, 3.5]"
+        );
+    }
+
+    #[test]
+    fn geo_conditions() {
+        let area = Geometry::Polygon(vec![vec![
+            (-0.38, 51.43),
+            (0.1, 51.43),
+            (0.1, 51.6),
+            (-0.38, 51.43),
+        ]]);
+        let sql = QueryBuilder::new()
+            .from("venue")
+            .where_complex(Condition::inside("location", area))
+            .where_complex(Condition::within_distance("location", (-0.12, 51.5), 1000))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM venue WHERE location INSIDE { type: 'Polygon', coordinates: \
+             [[[-0.38, 51.43], [0.1, 51.43], [0.1, 51.6], [-0.38, 51.43]]] } \
+             AND geo::distance(location, (-0.12, 51.5)) < 1000"
+        );
+    }
+
+    #[test]
+    fn subquery_conditions() {
+        let mut admins = QueryBuilder::new();
+        admins
+            .select("VALUE id", None)
+            .from("user")
+            .where_complex(Condition::eq("role", "admin"));
+        let mut orders = QueryBuilder::new();
+        orders
+            .select("id", None)
+            .from("order")
+            .where_simple("customer = $parent.id")
+            .limit(1);
+
+        let sql = QueryBuilder::new()
+            .from("post")
+            .where_complex(Condition::in_subquery("author", &admins).unwrap())
+            .where_complex(Condition::exists(&orders).unwrap().negate())
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM post WHERE author IN (SELECT VALUE id FROM user WHERE role = 'admin') \
+             AND !(array::len((SELECT id FROM order WHERE customer = $parent.id LIMIT 1)) > 0)"
+        );
+        assert!(Condition::exists(&QueryBuilder::new()).is_err());
+    }
+
+    #[test]
+    fn all_and_any_from_iterators() {
+        let filters = [("status", "open"), ("owner", "me")];
+        let cond = Condition::all(filters.iter().map(|(f, v)| Condition::eq(*f, *v)));
+        assert_eq!(
+            render_condition(&cond),
+            "(status = 'open' AND owner = 'me')"
+        );
+
+        let single = Condition::any([Condition::eq("a", 1)]);
+        assert_eq!(single, Condition::eq("a", 1));
+
+        assert_eq!(render_condition(&Condition::all(Vec::new())), "true");
+        assert_eq!(render_condition(&Condition::any(Vec::new())), "false");
+    }
+
+    #[test]
+    fn statements_render_incrementally() {
+        let mut sb = ScriptBuilder::new();
+        sb.comments(CommentMode::Strip)
+            .comment("seed")
+            .let_raw("a", "SELECT * FROM a")
+            .let_raw("b", "SELECT * FROM b /* second */")
+            .returning(vec![("a", "$a"), ("b", "$b")]);
+        let streamed: Vec<String> = sb.render_statements().map(Result::unwrap).collect();
+        assert_eq!(
+            streamed,
+            vec![
+                "LET $a = (SELECT * FROM a);",
+                "LET $b = (SELECT * FROM b);",
+                "RETURN { a: $a, b: $b };",
+            ]
+        );
+        assert_eq!(streamed.join("\n"), sb.build().unwrap());
+
+        let mut empty = ScriptBuilder::new();
+        let items: Vec<_> = empty
+            .require_return()
+            .let_raw("a", "1")
+            .render_statements()
+            .collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            *items[0].as_ref().unwrap_err(),
+            "A return object is required."
+        );
+
+        let mut tx = TransactionBuilder::new();
+        tx.begin().add_statement("CREATE a").commit();
+        assert_eq!(
+            tx.render_statements().collect::<Vec<_>>().join("\n"),
+            tx.build()
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_datetimes_render_as_literals() {
+        use chrono::TimeZone;
+        let since = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let sql = QueryBuilder::new()
+            .from("event")
+            .where_complex(Condition::gte("created_at", since))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM event WHERE created_at >= d'2024-01-01T00:00:00Z'"
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_datetimes_render_as_literals() {
+        let since = time::OffsetDateTime::from_unix_timestamp(1_704_067_200).unwrap();
+        assert_eq!(Value::from(since).to_surql(), "d'2024-01-01T00:00:00Z'");
+
+        let ancient = time::OffsetDateTime::from_unix_timestamp(-100_000_000_000).unwrap();
+        assert_eq!(Value::from(ancient).to_surql(), "d'-1199-02-15T14:13:20Z'");
+        let offset = time::OffsetDateTime::from_unix_timestamp_nanos(1_704_067_200_500_000_000)
+            .unwrap()
+            .to_offset(time::UtcOffset::from_hms(-5, -30, 0).unwrap());
+        assert_eq!(
+            Value::from(offset).to_surql(),
+            "d'2023-12-31T18:30:00.5-05:30'"
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids_render_as_literals() {
+        let id = uuid::Uuid::from_u128(0x0190_d7a1_5b2c_7e3f_9a4b_1c2d_3e4f_5a6b);
+        let sql = QueryBuilder::new()
+            .from("session")
+            .where_complex(Condition::eq("token", id))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM session WHERE token = u'0190d7a1-5b2c-7e3f-9a4b-1c2d3e4f5a6b'"
+        );
+        assert_eq!(
+            RecordId::new("session", id).to_surql(),
+            "session:u'0190d7a1-5b2c-7e3f-9a4b-1c2d3e4f5a6b'"
+        );
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn decimals_keep_precision() {
+        let price = rust_decimal::Decimal::new(1999, 2);
+        let total =
+            rust_decimal::Decimal::from_str_exact("0.1000000000000000000000000001").unwrap();
+        assert_eq!(Value::from(price).to_surql(), "19.99dec");
+        assert_eq!(
+            Value::from(total).to_surql(),
+            "0.1000000000000000000000000001dec"
+        );
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_values_render_stably() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        fn unquote(s: &str) -> String {
+            let mut out = String::new();
+            let mut chars = s[1..s.len() - 1].chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    assert!(!c.is_control() && c != '\'');
+                    out.push(c);
+                    continue;
+                }
+                out.push(match chars.next().unwrap() {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap()
+                    }
+                    other => other,
+                });
+            }
+            out
+        }
+
+        let mut seed: u64 = 0x5eed;
+        for _ in 0..500 {
+            let bytes: Vec<u8> = (0..256)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    (seed >> 56) as u8
+                })
+                .collect();
+            let mut u = Unstructured::new(&bytes);
+            let value = Value::arbitrary(&mut u).unwrap();
+            assert_eq!(value.to_surql(), value.clone().to_surql());
+            if let Value::String(s) = &value {
+                assert_eq!(&unquote(&value.to_surql()), s);
+            }
+            if let Ok(cond) = Condition::arbitrary(&mut u) {
+                let _ = render_condition(&cond);
+            }
+        }
+    }
+
+    #[test]
+    fn const_identifier_newtypes() {
+        const POST: TableName = TableName::new_const("post");
+        const AUTHOR: FieldName = FieldName::new_const("author");
+        let sql = QueryBuilder::new()
+            .from(&POST)
+            .where_complex(Condition::eq(&AUTHOR, "tobie"))
+            .fetch(&AUTHOR)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM post WHERE author = 'tobie' FETCH author"
+        );
+        assert!(TableName::new("user_2").is_ok());
+        assert_eq!(TableName::new("2user").unwrap_err(), "Invalid table name.");
+        assert_eq!(FieldName::new("a.b").unwrap_err(), "Invalid field name.");
+    }
+
+    #[test]
+    fn geometry_types_render_geojson() {
+        let square = |o: f64| {
+            LineString(vec![
+                Point(o, o),
+                Point(o + 1.0, o),
+                Point(o + 1.0, o + 1.0),
+                Point(o, o),
+            ])
+        };
+        assert_eq!(Value::from(Point(-0.1, 51.5)).to_surql(), "(-0.1, 51.5)");
+        assert_eq!(
+            Value::from(LineString(vec![Point(0.0, 0.0), Point(1.0, 1.0)])).to_surql(),
+            "{ type: 'LineString', coordinates: [[0.0, 0.0], [1.0, 1.0]] }"
+        );
+        let multi = MultiPolygon(vec![Polygon::new(square(0.0)), Polygon::new(square(5.0))]);
+        assert_eq!(
+            Value::from(multi).to_surql(),
+            "{ type: 'MultiPolygon', coordinates: [\
+             [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]], \
+             [[[5.0, 5.0], [6.0, 5.0], [6.0, 6.0], [5.0, 5.0]]]] }"
+        );
+    }
+
+    #[cfg(feature = "geo-types")]
+    #[test]
+    fn geo_types_convert() {
+        let line: geo_types::LineString<f64> = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 0.0)].into();
+        let polygon = Polygon::from(geo_types::Polygon::new(line, vec![]));
+        assert_eq!(
+            Geometry::from(polygon).to_surql(),
+            "{ type: 'Polygon', coordinates: [[[0.0, 0.0], [1.0, 0.0], [0.0, 0.0]]] }"
+        );
+    }
+
+    #[test]
+    fn detailed_transaction_spans() {
+        let mut tx = TransactionBuilder::new();
+        tx.begin()
+            .comment("move funds")
+            .add_statement("UPDATE account:a SET balance -= 10")
+            .label("debit")
+            .add_statement("UPDATE account:b SET balance += 10")
+            .label("credit")
+            .commit();
+        let detailed = tx.build_detailed();
+        assert_eq!(detailed.script, tx.build());
+        let summary: Vec<_> = detailed
+            .statements
+            .iter()
+            .map(|s| (s.label.as_deref(), s.kind, &detailed.script[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (None, StatementKind::Begin, "BEGIN TRANSACTION;"),
+                (
+                    Some("debit"),
+                    StatementKind::Update,
+                    "UPDATE account:a SET balance -= 10;"
+                ),
+                (
+                    Some("credit"),
+                    StatementKind::Update,
+                    "UPDATE account:b SET balance += 10;"
+                ),
+                (None, StatementKind::Commit, "COMMIT TRANSACTION;"),
+            ]
+        );
+
+        let mut tx = TransactionBuilder::new();
+        tx.begin()
+            .add_script(
+                "LET $n = 1; -- one\nIF $n { CREATE a; };\nRETURN 'x;y';\nRETURN t:⟨a\\⟩;b⟩;",
+            )
+            .label("script")
+            .commit();
+        let detailed = tx.build_detailed();
+        let summary: Vec<_> = detailed
+            .statements
+            .iter()
+            .map(|s| (s.label.as_deref(), &detailed.script[s.span.clone()]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (None, "BEGIN TRANSACTION;"),
+                (None, "LET $n = 1;"),
+                (None, "-- one\nIF $n { CREATE a; };"),
+                (None, "RETURN 'x;y';"),
+                (Some("script"), "RETURN t:⟨a\\⟩;b⟩;"),
+                (None, "COMMIT TRANSACTION;"),
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_conditions_and_strict_mode() {
+        let cond = Condition::binary("tags", Operator::ContainsAny, vec!["rust", "db"]);
+        assert_eq!(render_condition(&cond), "tags CONTAINSANY ['rust', 'db']");
+        assert_eq!(
+            Condition::eq("age", 18),
+            Condition::binary("age", Operator::Eq, 18)
+        );
+
+        let mut qb = QueryBuilder::new();
+        qb.from("post")
+            .strict()
+            .where_complex(cond)
+            .where_complex(Condition::raw("time::now() > published_at"));
+        assert_eq!(
+            qb.build().unwrap(),
+            "SELECT * FROM post WHERE tags CONTAINSANY ['rust', 'db'] AND time::now() > published_at"
+        );
+        qb.where_complex(Condition::Not(Box::new(Condition::Simple("draft".into()))));
+        assert_eq!(
+            qb.build().unwrap_err(),
+            "Simple conditions are not allowed in strict mode."
+        );
+    }
+
+    #[test]
+    fn build_with_generated_bindings() {
+        let mut inner = QueryBuilder::new();
+        inner
+            .from("user")
+            .where_complex(Condition::eq("name", "x' OR true"));
+        let mut qb = QueryBuilder::new();
+        qb.from(&inner)
+            .bind("p1", "taken")
+            .where_complex(Condition::any([
+                Condition::is_in("role", ["admin", "owner"]),
+                Condition::gte("age", param("min_age")),
+            ]));
+        let (sql, bindings) = qb.build_with_bindings().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT * FROM user WHERE name = $p0) WHERE (role IN $p2 OR age >= $min_age)"
+        );
+        assert_eq!(bindings["p0"], Value::from("x' OR true"));
+        assert_eq!(bindings["p1"], Value::from("taken"));
+        assert_eq!(bindings["p2"], Value::from(vec!["admin", "owner"]));
+        assert_eq!(bindings.len(), 3);
+    }
+
+    #[test]
+    fn string_literals_escape_control_characters() {
+        let hostile = "a'; DELETE user; --\nb\\\r\t\0\u{1b}[0m";
+        assert_eq!(
+            Value::from(hostile).to_surql(),
+            "'a\\'; DELETE user; --\\nb\\\\\\r\\t\\u0000\\u001b[0m'"
+        );
+        let sql = QueryBuilder::new()
+            .from("user")
+            .where_complex(Condition::eq("name", "line1\nline2"))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM user WHERE name = 'line1\\nline2'");
+        assert!(!sql.contains('\n'));
+    }
+
+    #[test]
+    fn identifiers_are_escaped() {
+        let sql = QueryBuilder::new()
+            .select_ident("group", None)
+            .select_ident("first name", Some("name"))
+            .from(Ident::new("user-profile"))
+            .where_complex(Condition::eq(Ident::new("order"), 1))
+            .order_by_ident("select", Order::Asc)
+            .fetch_ident("from")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT `group`, `first name` AS name FROM `user-profile` WHERE `order` = 1 \
+             ORDER BY `select` ASC FETCH `from`"
+        );
+        assert_eq!(Ident::new("email").to_string(), "email");
+        assert_eq!(Ident::new("a`b").to_surql(), "`a\\`b`");
+        let sql = QueryBuilder::new()
+            .select_ident("a\\", None)
+            .from("user")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT `a\\\\` FROM user");
+        assert_eq!(Ident::new("a\\`b").to_surql(), "`a\\\\\\`b`");
+    }
+
+    #[test]
+    fn raw_escape_hatch() {
+        let mut qb = QueryBuilder::new();
+        qb.from(Raw::new("type::table($tb)"))
+            .strict()
+            .where_complex(Condition::gt("created_at", Raw::new("time::now() - 1d")))
+            .where_complex(Raw::new("array::len(tags) > 2"))
+            .where_complex(Condition::eq("name", "time::now()"));
+        assert_eq!(
+            qb.build().unwrap(),
+            "SELECT * FROM type::table($tb) WHERE created_at > time::now() - 1d \
+             AND array::len(tags) > 2 AND name = 'time::now()'"
+        );
+        let (sql, bindings) = qb.build_with_bindings().unwrap();
+        assert!(
+            sql.ends_with("created_at > time::now() - 1d AND array::len(tags) > 2 AND name = $p0")
+        );
+        assert_eq!(bindings.len(), 1);
+    }
+
+    #[test]
+    fn script_let_value() {
+        let config = Value::Object(vec![
+            ("retries".into(), 3.into()),
+            ("tags".into(), vec!["a", "b"].into()),
+        ]);
+        let mut sb = ScriptBuilder::new();
+        sb.read_only()
+            .let_value("config", config)
+            .let_value("$limit", Duration::from_secs(90));
+        assert_eq!(
+            sb.build().unwrap(),
+            "LET $config = { retries: 3, tags: ['a', 'b'] };\nLET $limit = 1m30s;"
+        );
+        assert!(sb.let_value("x", Raw::new("DELETE user")).build().is_err());
+    }
+
+    #[test]
+    fn script_returns_single_expression() {
+        let mut latest = QueryBuilder::new();
+        latest.from("post").order_by("created DESC").limit(5);
+        let mut sb = ScriptBuilder::new();
+        sb.let_raw("n", "count(SELECT * FROM post)")
+            .return_query(&latest)
+            .unwrap();
+        assert_eq!(
+            sb.build().unwrap(),
+            "LET $n = (count(SELECT * FROM post));\n\
+             RETURN (SELECT * FROM post ORDER BY created DESC LIMIT 5); "
+        );
+        assert!(
+            sb.return_expr("$n")
+                .require_return()
+                .build()
+                .unwrap()
+                .ends_with("\nRETURN $n; ")
+        );
+        assert!(sb.read_only().build().is_ok());
+        assert!(sb.return_expr("(DELETE user)").build().is_err());
+    }
+
+    #[test]
+    fn script_return_nested_subqueries_are_checked() {
+        let mut create = QueryBuilder::new();
+        create.from("user");
+        let mut sb = ScriptBuilder::new();
+        sb.read_only().returning(vec![(
+            "meta",
+            ReturnValue::object(vec![(
+                "users",
+                ReturnValue::array(vec![ReturnValue::query(&create).unwrap()]),
+            )]),
+        )]);
+        assert_eq!(
+            sb.build().unwrap(),
+            "RETURN { meta: { users: [(SELECT * FROM user)] } }; "
+        );
+        sb.returning(vec![("new", ReturnValue::Query("CREATE user".into()))]);
+        assert!(sb.build().is_err());
+        sb.returning(vec![("x", "(DELETE user)")]);
+        assert!(sb.build().is_err());
+        sb.returning(vec![("x", "$x")]);
+        assert!(sb.build().is_ok());
+    }
+
+    #[test]
+    fn script_without_return() {
+        let mut sb = ScriptBuilder::new();
+        sb.let_raw("admin", "CREATE user:admin SET role = 'admin'")
+            .let_raw("guest", "CREATE user:guest SET role = 'guest'");
+        assert_eq!(
+            sb.build().unwrap(),
+            "LET $admin = (CREATE user:admin SET role = 'admin');\n\
+             LET $guest = (CREATE user:guest SET role = 'guest');"
+        );
+        assert_eq!(
+            sb.require_return().build().unwrap_err(),
+            "A return object is required."
+        );
+    }
+
+    #[test]
+    fn bound_parameters() {
+        let untrusted = "x' OR true --";
+        let mut qb = QueryBuilder::new();
+        qb.from("user")
+            .where_complex(Condition::eq("name", param("name")))
+            .where_complex(Condition::gte("age", Value::param("$min_age")))
+            .bind("name", untrusted);
+        assert_eq!(
+            qb.build().unwrap(),
+            "SELECT * FROM user WHERE name = $name AND age >= $min_age"
+        );
+        assert_eq!(qb.bindings().get("name"), Some(&Value::from(untrusted)));
+        assert_eq!(
+            qb.unbound_parameters().into_iter().collect::<Vec<_>>(),
+            vec!["min_age"]
+        );
+        qb.bind("$min_age", 18);
+        assert!(qb.unbound_parameters().is_empty());
+
+        let mut outer = QueryBuilder::new();
+        outer.from(&qb).bind("name", "override");
+        assert_eq!(outer.bindings().get("min_age"), Some(&Value::Int(18)));
+        assert_eq!(outer.bindings().get("name"), Some(&Value::from("override")));
+    }
+
+    #[test]
+    fn value_literals() {
+        assert_eq!(Value::None.to_surql(), "NONE");
+        assert_eq!(Value::from(None::<i64>).to_surql(), "NONE");
+        assert_eq!(Value::from(Some("x")).to_surql(), "'x'");
+        assert_eq!(Value::Null.to_surql(), "NULL");
+        assert_eq!(Value::from(Duration::from_secs(5400)).to_surql(), "1h30m");
+        assert_eq!(
+            Value::from(Duration::from_millis(90_061_500)).to_surql(),
+            "1d1h1m1s500ms"
+        );
+        assert_eq!(Value::from(Duration::ZERO).to_surql(), "0ns");
+        assert_eq!(Value::Float(1.0).to_surql(), "1.0");
+        assert_eq!(Value::Float(f64::INFINITY).to_surql(), "math::inf");
+        assert_eq!(Value::Float(f64::NEG_INFINITY).to_surql(), "math::neg_inf");
+        assert_eq!(Value::Float(f64::NAN).to_surql(), "math::nan");
+        let obj = Value::Object(vec![
+            ("name".into(), "Tobie".into()),
+            ("tags".into(), vec!["a", "b"].into()),
+            ("owner".into(), RecordId::new("user", 1).into()),
+            ("since".into(), Value::datetime("2024-01-01T00:00:00Z")),
+        ]);
+        assert_eq!(
+            obj.to_surql(),
+            "{ name: 'Tobie', tags: ['a', 'b'], owner: user:1, since: d'2024-01-01T00:00:00Z' }"
+        );
+    }
+
+    #[test]
+    fn nested_groups_render_flat() {
+        let cond = Condition::And(vec![
+            Condition::And(vec![
+                Condition::Simple("a = 1".into()),
+                Condition::Simple("b = 2".into()),
+            ]),
+            Condition::Simple("c = 3".into()),
+            Condition::Or(vec![Condition::Simple("d = 4".into())]),
+        ]);
+        assert_eq!(
+            render_condition(&cond),
+            "(a = 1 AND b = 2 AND c = 3 AND d = 4)"
+        );
+
+        let single = Condition::Or(vec![Condition::Simple("a = 1".into())]);
+        assert_eq!(render_condition(&single), "a = 1");
+        assert_eq!(render_condition(&single.negate()), "!(a = 1)");
+        let mixed = Condition::Or(vec![
+            Condition::Simple("a = 1".into()),
+            Condition::And(vec![
+                Condition::Simple("b = 2".into()),
+                Condition::Simple("c = 3".into()),
+            ]),
+        ]);
+        assert_eq!(
+            render_condition(&mixed.negate()),
+            "!(a = 1 OR (b = 2 AND c = 3))"
+        );
+    }
+
+    #[test]
+    fn map_inputs_render_deterministically() {
+        let filters: HashMap<&str, Value> = [
+            ("status", Value::from("open")),
+            ("archived", Value::from(false)),
+            ("priority", Value::from(2)),
+        ]
+        .into_iter()
+        .collect();
+        let sql = QueryBuilder::new()
+            .from("ticket")
+            .where_map(filters)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM ticket WHERE archived = false AND priority = 2 AND status = 'open'"
+        );
+
+        let map: HashMap<&str, i64> = [("zeta", 1), ("alpha", 2), ("my key", 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            Value::from(map).to_surql(),
+            "{ alpha: 2, 'my key': 3, zeta: 1 }"
+        );
+        let ordered = Value::Object(vec![("b".into(), 1.into()), ("a".into(), 2.into())]);
+        assert_eq!(ordered.to_surql(), "{ b: 1, a: 2 }");
+    }
+
+    #[test]
+    fn build_errors_carry_suggestions() {
+        let err = QueryBuilder::new()
+            .select_fields(&["name"])
+            .build()
+            .unwrap_err();
+        assert_eq!(err.suggestion, Some("call .from(table) before .build()"));
+        assert_eq!(
+            err.to_string(),
+            "The FROM clause is required. Suggestion: call .from(table) before .build()."
+        );
+    }
+
+    #[test]
+    fn missing_from_clause() {
+        let err = QueryBuilder::new().build().unwrap_err();
+        assert_eq!(err, "The FROM clause is required.");
+    }
+
+    #[test]
+    fn multi_fetch_and_graph_expand() {
+        let sql = QueryBuilder::new()
+            .from("tbl")
+            .fetch("a")
+            .fetch("b")
+            .graph_expand("x")
+            .graph_expand("y")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT *, x, y FROM tbl FETCH a, b");
+    }
+
+    #[test]
+    fn select_and_graph_expand() {
+        let sql = QueryBuilder::new()
+            .select("foo", None)
+            .graph_expand("bar")
+            .from("t")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT foo, bar FROM t");
+    }
+
+    #[test]
+    fn where_and_where_complex_equal() {
+        let simple = QueryBuilder::new()
+            .from("t")
+            .where_simple("x = 1")
+            .build()
+            .unwrap();
+        let complex = QueryBuilder::new()
+            .from("t")
+            .where_complex(Condition::Simple("x = 1".into()))
+            .build()
+            .unwrap();
+        assert_eq!(simple, complex);
+    }
+
+    #[test]
+    fn full_chaining_all_clauses() {
+        let sql = QueryBuilder::new()
+            .select("a", None)
+            .from("t")
+            .where_simple("w")
+            .fetch("f")
+            .graph_expand("g")
+            .order_by("o")
+            .limit(1)
+            .start(2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT a, g FROM t WHERE w ORDER BY o LIMIT 1 START 2 FETCH f"
+        );
+    }
+
+    #[test]
+    fn graph_traverse_example() {
+        let sql = QueryBuilder::new()
+            .from("user")
+            .graph_traverse(GraphExpandParams {
+                from: (Direction::Out, "friends".into()),
+                to: (Direction::In, "posts".into()),
+                projection: Some("*".into()),
+                alias: Some("friend_posts".into()),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM user ->friends<-posts.* AS friend_posts");
+    }
+
+    #[test]
+    fn graph_traverse_alias_can_be_fetched() {
+        let sql = QueryBuilder::new()
+            .from("user")
+            .graph_traverse(GraphExpandParams {
+                from: (Direction::Out, "likes".into()),
+                to: (Direction::Out, "post".into()),
+                projection: None,
+                alias: Some("liked-posts".into()),
+            })
+            .fetch_alias("liked-posts")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM user ->likes->post AS `liked-posts` FETCH `liked-posts`"
+        );
+
+        let sql = QueryBuilder::new()
+            .select("->likes->post", Some("liked posts"))
+            .from("user")
+            .fetch_alias("liked posts")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT ->likes->post AS `liked posts` FROM user FETCH `liked posts`"
+        );
+    }
+
+    #[test]
+    fn graph_traverse_in_out() {
+        let sql = QueryBuilder::new()
+            .from("x")
+            .graph_traverse(GraphExpandParams {
+                from: (Direction::In, "t".into()),
+                to: (Direction::Out, "e".into()),
+                projection: None,
+                alias: None,
+            })
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM x <-t->e");
+    }
+
+    #[test]
+    fn from_numeric_range() {
+        let sql = QueryBuilder::new()
+            .from_range("person", 1, 1000)
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM person:1..1000");
+    }
+
+    #[test]
+    fn from_array_range_inclusive() {
+        let sql = QueryBuilder::new()
+            .from_range_inclusive(
+                "temperature",
+                vec![Value::from("London"), Value::datetime("2024-01-01")],
+                vec![Value::from("London"), Value::datetime("2024-02-01")],
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM temperature:['London', d'2024-01-01']..=['London', d'2024-02-01']"
+        );
+    }
+
+    #[test]
+    fn record_id_key_escaping() {
+        assert_eq!(RecordIdKey::from("tobie").to_surql(), "tobie");
+        assert_eq!(RecordIdKey::from("123").to_surql(), "⟨123⟩");
+        assert_eq!(RecordIdKey::from("a-b c").to_surql(), "⟨a-b c⟩");
+        assert_eq!(RecordIdKey::from("x\\").to_surql(), "⟨x\\\\⟩");
+        assert_eq!(
+            RecordId::new("user", "x\\⟩; DELETE user; --").to_surql(),
+            "user:⟨x\\\\\\⟩; DELETE user; --⟩"
+        );
+        assert_eq!(Value::from("it's").to_surql(), "'it\\'s'");
+    }
+
+    #[test]
+    fn subquery_as_from_target() {
+        let mut inner = QueryBuilder::new();
+        inner
+            .select("country", None)
+            .select("count()", Some("total"))
+            .from("person")
+            .group_by("country");
+        let sql = QueryBuilder::new()
+            .from(&inner)
+            .where_simple("total > 10")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT country, count() AS total FROM person GROUP BY country) WHERE total > 10"
+        );
+
+        let sql = QueryBuilder::new()
+            .from(Target::raw_subquery("SELECT * FROM person"))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM (SELECT * FROM person)");
+    }
+
+    #[test]
+    fn subquery_without_from_fails() {
+        let err = QueryBuilder::new()
+            .from(QueryBuilder::new())
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "The FROM clause is required.");
+    }
+
+    #[test]
+    fn from_param_target() {
+        let mut qb = QueryBuilder::new();
+        qb.from_param("$things").where_simple("owner = $user");
+        assert_eq!(
+            qb.build().unwrap(),
+            "SELECT * FROM $things WHERE owner = $user"
+        );
+        let params: Vec<String> = qb.parameters().into_iter().collect();
+        assert_eq!(params, vec!["things", "user"]);
+
+        let sql = QueryBuilder::new().from_param("items").build().unwrap();
+        assert_eq!(sql, "SELECT * FROM $items");
+    }
+
+    #[test]
+    fn record_id_rendering_and_from() {
+        assert_eq!(RecordId::new("user", 123).to_surql(), "user:123");
+        assert_eq!(
+            RecordId::new("user", "john@doe.com").to_surql(),
+            "user:⟨john@doe.com⟩"
+        );
+        assert_eq!(
+            RecordId::new("user-profile", "a⟩b").to_string(),
+            "⟨user-profile⟩:⟨a\\⟩b⟩"
+        );
 
-    #[test]
-    fn simple_select_from() {
-        let sql = QueryBuilder::new().from("user").build().unwrap();
-        assert_eq!(sql, "SELECT * FROM user");
+        let sql = QueryBuilder::new()
+            .from(RecordId::new("user", "tobie"))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM user:tobie");
+        assert_eq!(Value::from(RecordId::new("user", 1)).to_surql(), "user:1");
     }
 
     #[test]
-    fn select_where_order_limit_start() {
+    fn complex_record_ids() {
+        let reading = RecordId::new(
+            "reading",
+            vec![
+                ("city".to_string(), Value::from("London")),
+                ("day".to_string(), Value::from(1)),
+            ],
+        );
+        assert_eq!(reading.to_surql(), "reading:{ city: 'London', day: 1 }");
+        assert_eq!(
+            RecordId::from(("temp", vec![Value::from(1), Value::from("a")])).to_surql(),
+            "temp:[1, 'a']"
+        );
+
         let sql = QueryBuilder::new()
-            .select("id, name", None)
-            .from("user")
-            .where_simple("active = true")
-            .order_by("name ASC")
-            .limit(5)
-            .start(10)
+            .from(("user", "weird id"))
+            .where_complex(Condition::eq("best_friend", &reading))
             .build()
             .unwrap();
         assert_eq!(
             sql,
-            "SELECT id, name FROM user WHERE active = true ORDER BY name ASC LIMIT 5 START 10"
+            "SELECT * FROM user:⟨weird id⟩ WHERE best_friend = reading:{ city: 'London', day: 1 }"
         );
     }
 
     #[test]
-    fn fetch_and_graph_expand() {
+    fn tempfiles_keyword() {
         let sql = QueryBuilder::new()
-            .from("post")
-            .fetch("comments")
-            .graph_expand("likes")
+            .from("sale")
+            .order_by("amount DESC")
+            .fetch("customer")
+            .tempfiles()
+            .explain()
             .build()
             .unwrap();
-        assert_eq!(sql, "SELECT *, likes FROM post FETCH comments");
+        assert_eq!(
+            sql,
+            "SELECT * FROM sale ORDER BY amount DESC FETCH customer TEMPFILES EXPLAIN"
+        );
     }
 
     #[test]
-    fn complex_where_conditions() {
-        let cond = Condition::And(vec![
-            Condition::Simple("a = 1".into()),
-            Condition::Or(vec![
-                Condition::Simple("b = 2".into()),
-                Condition::Simple("c = 3".into()),
-            ]),
-        ]);
+    fn explain_and_explain_full() {
         let sql = QueryBuilder::new()
-            .from("t")
-            .where_complex(cond)
+            .from("user")
+            .where_simple("email = $email")
+            .explain()
             .build()
             .unwrap();
-        assert_eq!(sql, "SELECT * FROM t WHERE (a = 1 AND (b = 2 OR c = 3))");
-    }
-
-    #[test]
-    fn missing_from_clause() {
-        let err = QueryBuilder::new().build().unwrap_err();
-        assert_eq!(err, "The FROM clause is required.");
-    }
+        assert_eq!(sql, "SELECT * FROM user WHERE email = $email EXPLAIN");
 
-    #[test]
-    fn multi_fetch_and_graph_expand() {
         let sql = QueryBuilder::new()
-            .from("tbl")
-            .fetch("a")
-            .fetch("b")
-            .graph_expand("x")
-            .graph_expand("y")
+            .from("user")
+            .fetch("org")
+            .explain_full()
             .build()
             .unwrap();
-        assert_eq!(sql, "SELECT *, x, y FROM tbl FETCH a, b");
+        assert_eq!(sql, "SELECT * FROM user FETCH org EXPLAIN FULL");
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn select_and_graph_expand() {
-        let sql = QueryBuilder::new()
-            .select("foo", None)
-            .graph_expand("bar")
-            .from("t")
-            .build()
-            .unwrap();
-        assert_eq!(sql, "SELECT foo, bar FROM t");
+    fn explain_report_deserializes() {
+        let json = r#"[
+            {"detail": {"plan": {"index": "email_idx", "operator": "=", "value": "a@b.c"}, "table": "user"}, "operation": "Iterate Index"},
+            {"detail": {"type": "Memory"}, "operation": "Collector"}
+        ]"#;
+        let reports: Vec<ExplainReport> = serde_json::from_str(json).unwrap();
+        assert_eq!(reports[0].index(), Some("email_idx"));
+        assert_eq!(reports[0].detail.table.as_deref(), Some("user"));
+        assert!(ExplainReport::uses_index(&reports, "email_idx"));
+        assert!(!ExplainReport::uses_index(&reports, "name_idx"));
     }
 
     #[test]
-    fn where_and_where_complex_equal() {
-        let simple = QueryBuilder::new()
-            .from("t")
-            .where_simple("x = 1")
+    fn typed_order_by() {
+        let sql = QueryBuilder::new()
+            .from("user")
+            .order_by_field("name", Order::Desc)
+            .order_by_numeric("code", Order::Asc)
+            .order_by_collate("title", Order::Asc)
             .build()
             .unwrap();
-        let complex = QueryBuilder::new()
-            .from("t")
-            .where_complex(Condition::Simple("x = 1".into()))
+        assert_eq!(
+            sql,
+            "SELECT * FROM user ORDER BY name DESC, code NUMERIC ASC, title COLLATE ASC"
+        );
+
+        let sql = QueryBuilder::new()
+            .from("user")
+            .order_by_rand()
+            .limit(1)
             .build()
             .unwrap();
-        assert_eq!(simple, complex);
+        assert_eq!(sql, "SELECT * FROM user ORDER BY RAND() LIMIT 1");
     }
 
     #[test]
-    fn full_chaining_all_clauses() {
+    fn path_conditions() {
         let sql = QueryBuilder::new()
-            .select("a", None)
-            .from("t")
-            .where_simple("w")
-            .fetch("f")
-            .graph_expand("g")
-            .order_by("o")
-            .limit(1)
-            .start(2)
+            .from("user")
+            .where_complex(path("address").field("city").eq("Berlin"))
+            .where_complex(Path::from("stats.login-count").gte(3))
             .build()
             .unwrap();
         assert_eq!(
             sql,
-            "SELECT a, g FROM t WHERE w ORDER BY o LIMIT 1 START 2 FETCH f"
+            "SELECT * FROM user WHERE address.city = 'Berlin' AND stats.`login-count` >= 3"
         );
+        assert_eq!(Path::new("a.b").to_surql(), "`a.b`");
     }
 
     #[test]
-    fn graph_traverse_example() {
+    fn path_index_and_slice_projections() {
         let sql = QueryBuilder::new()
-            .from("user")
-            .graph_traverse(GraphExpandParams {
-                from: (Direction::Out, "friends".into()),
-                to: (Direction::In, "posts".into()),
-                alias: Some("friend_posts".into()),
-            })
+            .select_path(&path("tags").index(0), Some("first_tag"))
+            .select_path(&path("events").slice(..3), None)
+            .select_path(&path("events").last().field("at"), None)
+            .select_path(
+                &path("items").filter(Condition::Simple("qty > 1".into())),
+                Some("bulk"),
+            )
+            .from("order")
             .build()
             .unwrap();
-        assert_eq!(sql, "SELECT * FROM user ->friends->posts.* AS friend_posts");
+        assert_eq!(
+            sql,
+            "SELECT tags[0] AS first_tag, events[..3], events[$].at, items[WHERE qty > 1] AS bulk FROM order"
+        );
+        assert_eq!(path("a").slice(1..=2).all().to_surql(), "a[1..=2][*]");
     }
 
     #[test]
-    fn graph_traverse_in_out() {
+    fn paginate_sets_limit_and_start() {
         let sql = QueryBuilder::new()
-            .from("x")
-            .graph_traverse(GraphExpandParams {
-                from: (Direction::In, "t".into()),
-                to: (Direction::Out, "e".into()),
-                alias: None,
-            })
+            .from("post")
+            .paginate(3, 20)
             .build()
             .unwrap();
-        assert_eq!(sql, "SELECT * FROM x <-t->e.*");
+        assert_eq!(sql, "SELECT * FROM post LIMIT 20 START 40");
+
+        let first = QueryBuilder::new().from("post").paginate(1, 20).build();
+        let zero = QueryBuilder::new().from("post").paginate(0, 20).build();
+        assert_eq!(first, zero);
+        assert_eq!(first.unwrap(), "SELECT * FROM post LIMIT 20 START 0");
     }
 
     #[test]
@@ -613,6 +5038,111 @@ mod tests {
         assert_eq!(sql, "SELECT count() FROM person GROUP ALL");
     }
 
+    #[test]
+    fn aggregate_helpers() {
+        let sql = QueryBuilder::new()
+            .select("country", None)
+            .select_count(Some("total"))
+            .select_sum("price", Some("revenue"))
+            .select_avg("price", None)
+            .select_min("price", Some("cheapest"))
+            .select_max("price", Some("priciest"))
+            .from("order")
+            .group_by("country")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT country, count() AS total, math::sum(price) AS revenue, math::mean(price), \
+             math::min(price) AS cheapest, math::max(price) AS priciest FROM order GROUP BY country"
+        );
+    }
+
+    #[test]
+    fn having_wraps_grouped_query() {
+        let sql = QueryBuilder::new()
+            .select("country", None)
+            .select_count(Some("total"))
+            .from("person")
+            .where_simple("active = true")
+            .group_by("country")
+            .having(Condition::gt("total", 100))
+            .order_by("total DESC")
+            .limit(5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT country, count() AS total FROM person WHERE active = true \
+             GROUP BY country) WHERE total > 100 ORDER BY total DESC LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn select_subquery_projection() {
+        let mut inner = QueryBuilder::new();
+        inner
+            .select_count(None)
+            .from("post")
+            .where_simple("author = $parent.id");
+        let sql = QueryBuilder::new()
+            .select("name", None)
+            .select_subquery(&inner, Some("posts"))
+            .unwrap()
+            .from("user")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name, (SELECT count() FROM post WHERE author = $parent.id) AS posts FROM user"
+        );
+        assert!(
+            QueryBuilder::new()
+                .select_subquery(&QueryBuilder::new(), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn join_style_subqueries() {
+        let mut company = QueryBuilder::new();
+        company.select("name", None).from("company");
+        let mut tags = QueryBuilder::new();
+        tags.select("VALUE name", None)
+            .from("tag")
+            .where_simple("hidden = false")
+            .order_by("name ASC");
+
+        let sql = QueryBuilder::new()
+            .select("name", None)
+            .select_join(&company, "employer", Some("company"))
+            .unwrap()
+            .select_join_many(&tags, "tags", Some("tags"))
+            .unwrap()
+            .from("person")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name, (SELECT name FROM company WHERE id = $parent.employer) AS company, \
+             (SELECT VALUE name FROM tag WHERE hidden = false AND id IN $parent.tags ORDER BY name ASC) \
+             AS tags FROM person"
+        );
+
+        let mut strict = QueryBuilder::new();
+        strict.select("name", None).from("company").strict();
+        let sql = QueryBuilder::new()
+            .select_join(&strict, "my-employer", Some("company"))
+            .unwrap()
+            .from("person")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT (SELECT name FROM company WHERE id = $parent.`my-employer`) AS company FROM person"
+        );
+    }
+
     #[test]
     fn multiple_selects() {
         let sql = QueryBuilder::new()
@@ -624,6 +5154,33 @@ mod tests {
         assert_eq!(sql, "SELECT id, name FROM users");
     }
 
+    #[test]
+    fn select_fields_pushes_each_field() {
+        let sql = QueryBuilder::new()
+            .select_fields(&["id", "name"])
+            .select_fields(&["email"])
+            .from("user")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT id, name, email FROM user");
+    }
+
+    #[test]
+    fn nested_destructuring_projections() {
+        let sql = QueryBuilder::new()
+            .select("name", None)
+            .select_nested("address", &["city", "country"])
+            .select_nested("->likes->post", &["title"])
+            .select_path(&path("profile").destructure(&["avatar", "zip-code"]), None)
+            .from("user")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name, address.{city, country}, ->likes->post.{title}, profile.{avatar, `zip-code`} FROM user"
+        );
+    }
+
     #[test]
     fn select_with_alias() {
         let sql = QueryBuilder::new()
@@ -670,6 +5227,103 @@ mod tests {
         assert_eq!(script, expected);
     }
 
+    #[test]
+    fn typed_accessor_suffix() {
+        let mut qb = QueryBuilder::new();
+        qb.select("count()", None).from("widget").group_all();
+
+        let mut typed = super::ScriptBuilder::new();
+        typed
+            .let_query_with_suffix("n", &qb, accessor().index(0).field("count"))
+            .unwrap()
+            .returning(vec![("count", "$n")]);
+        let mut raw = super::ScriptBuilder::new();
+        raw.let_query_with_suffix("n", &qb, "[0].count")
+            .unwrap()
+            .returning(vec![("count", "$n")]);
+        assert_eq!(typed.build().unwrap(), raw.build().unwrap());
+
+        let mut tb = super::TransactionBuilder::new();
+        tb.add_query_with_suffix(&qb, accessor().last().field("count"))
+            .unwrap();
+        assert_eq!(
+            tb.build(),
+            "(SELECT count() FROM widget GROUP ALL)[$].count;"
+        );
+    }
+
+    #[test]
+    fn read_only_script_rejects_writes() {
+        let mut qb = QueryBuilder::new();
+        qb.from("widget");
+
+        let mut sb = super::ScriptBuilder::new();
+        sb.read_only()
+            .let_query("widgets", &qb)
+            .unwrap()
+            .let_raw("info", "INFO FOR DB")
+            .returning(vec![("widgets", "$widgets")]);
+        assert!(sb.build().is_ok());
+
+        sb.let_raw("gone", "DELETE widget");
+        assert_eq!(
+            sb.build().unwrap_err(),
+            "Read-only scripts may only contain SELECT, INFO or LIVE statements."
+        );
+    }
+
+    #[test]
+    fn read_only_script_rejects_smuggled_writes() {
+        for expr in [
+            "SELECT * FROM user); DELETE user; (SELECT 1",
+            "SELECT * FROM (DELETE user RETURN BEFORE)",
+            "count(SELECT * FROM (update user SET x = 1))",
+            "fn::purge()",
+            "SELECT * FROM user WHERE (a",
+        ] {
+            let mut sb = super::ScriptBuilder::new();
+            sb.read_only().let_raw("x", expr);
+            assert!(sb.build().is_err(), "{expr}");
+        }
+
+        let mut sb = super::ScriptBuilder::new();
+        sb.read_only()
+            .let_raw("n", "count(SELECT * FROM user WHERE note = 'delete; me')")
+            .let_raw(
+                "m",
+                "SELECT `update` FROM log WHERE $delete = array::insert($a, 1)",
+            );
+        assert!(sb.build().is_ok());
+
+        let mut sb = super::ScriptBuilder::new();
+        sb.read_only()
+            .comment("x\nDELETE user\rDELETE post")
+            .let_raw("n", "count(SELECT * FROM user)");
+        assert_eq!(
+            sb.build().unwrap(),
+            "-- x\n-- DELETE user\n-- DELETE post\nLET $n = (count(SELECT * FROM user));"
+        );
+    }
+
+    #[test]
+    fn statement_kinds() {
+        assert_eq!(QueryBuilder::new().kind(), StatementKind::Select);
+        assert_eq!(super::ScriptBuilder::new().kind(), StatementKind::Script);
+        assert_eq!(
+            super::TransactionBuilder::new().kind(),
+            StatementKind::Transaction
+        );
+        assert_eq!(
+            StatementKind::of("  (select * from t)"),
+            StatementKind::Select
+        );
+        assert_eq!(StatementKind::of("DEFINE TABLE t"), StatementKind::Define);
+        assert_eq!(StatementKind::of("UPSERT t:1"), StatementKind::Upsert);
+        assert_eq!(StatementKind::of(""), StatementKind::Other);
+        assert!(StatementKind::of("INFO FOR DB").is_read_only());
+        assert!(!StatementKind::of("DELETE t").is_read_only());
+    }
+
     #[test]
     fn transaction_builder_commit_example() {
         let mut qb_create1 = QueryBuilder::new();
@@ -702,6 +5356,71 @@ mod tests {
         assert!(script.contains("CREATE widget:one SET value = 100;"));
     }
 
+    #[test]
+    fn transaction_retry_wrapper() {
+        let mut tb = super::TransactionBuilder::new();
+        tb.begin()
+            .add_statement("UPDATE account:a SET balance -= 10")
+            .add_statement("UPDATE account:b SET balance += 10")
+            .commit();
+
+        let retry = tb.with_retry("transfer-42", 3);
+        assert_eq!(
+            retry.script,
+            "BEGIN TRANSACTION;\n\
+             IF record::exists(idempotency:⟨transfer-42⟩) { THROW 'idempotency key already applied: transfer-42'; };\n\
+             CREATE idempotency:⟨transfer-42⟩ SET applied_at = time::now();\n\
+             UPDATE account:a SET balance -= 10;\n\
+             UPDATE account:b SET balance += 10;\n\
+             COMMIT TRANSACTION;"
+        );
+        let conflict = "Failed to commit transaction due to a read or write conflict. This transaction can be retried";
+        assert!(retry.should_retry(1, conflict));
+        assert!(!retry.should_retry(3, conflict));
+        assert!(!retry.should_retry(1, "Found NONE for field `balance`"));
+        assert!(
+            retry.is_already_applied(
+                "An error occurred: idempotency key already applied: transfer-42"
+            )
+        );
+    }
+
+    #[test]
+    fn comment_preserve_and_strip() {
+        let mut tb = super::TransactionBuilder::new();
+        tb.begin()
+            .comment("move funds")
+            .add_statement("UPDATE account:a SET note = 'a -- b # c' /* inline */")
+            .add_statement("// reset counter\nUPDATE ⟨odd#id⟩:x SET n = 1")
+            .commit();
+        assert!(tb.build().contains("-- move funds\n"));
+
+        tb.comments(CommentMode::Strip);
+        assert_eq!(
+            tb.build(),
+            "BEGIN TRANSACTION;\n\
+             UPDATE account:a SET note = 'a -- b # c';\n\
+             UPDATE ⟨odd#id⟩:x SET n = 1;\n\
+             COMMIT TRANSACTION;"
+        );
+
+        let mut sb = super::ScriptBuilder::new();
+        sb.comments(CommentMode::Strip)
+            .comment("totals")
+            .let_raw("n", "SELECT count() /* all rows */ FROM t")
+            .returning(vec![("n", "$n")]);
+        assert_eq!(
+            sb.build().unwrap(),
+            "LET $n = (SELECT count() FROM t);\nRETURN { n: $n };"
+        );
+
+        assert_eq!(
+            strip_comments("CREATE a SET b = \"x  \n\ny\";  \n\n-- done\n"),
+            "CREATE a SET b = \"x  \n\ny\";"
+        );
+        assert_eq!(strip_comments("t:⟨a\\⟩-- b⟩ -- c"), "t:⟨a\\⟩-- b⟩");
+    }
+
     #[test]
     fn transaction_with_two_selects_and_return() {
         let mut qb1 = QueryBuilder::new();
@@ -0,0 +1,176 @@
+//! Builders for live queries: `LIVE SELECT` to subscribe to changes and
+//! `KILL` to stop.
+
+use crate::{
+    BuildError, Condition, StatementKind, Value, dedup_conditions, escape_ident, quote_string,
+    render_condition,
+};
+
+/// What a live query sends for each change.
+#[derive(Debug, Clone, PartialEq)]
+enum LiveProjection {
+    Fields(Vec<String>),
+    Value(String),
+    Diff,
+}
+
+/// Builder for `LIVE SELECT` statements.
+#[derive(Debug, Clone)]
+pub struct LiveQueryBuilder {
+    table: String,
+    projection: LiveProjection,
+    where_clauses: Vec<Condition>,
+    fetch_clauses: Vec<String>,
+}
+
+impl LiveQueryBuilder {
+    /// Starts a live query over `table`, sending whole records (`*`) unless
+    /// fields are selected.
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            projection: LiveProjection::Fields(Vec::new()),
+            where_clauses: Vec::new(),
+            fetch_clauses: Vec::new(),
+        }
+    }
+
+    /// The kind of statement this builder produces.
+    pub fn kind(&self) -> StatementKind {
+        StatementKind::Live
+    }
+
+    /// Adds a field to the projection. Can be called multiple times.
+    pub fn select(&mut self, field: &str) -> &mut Self {
+        match &mut self.projection {
+            LiveProjection::Fields(fields) => fields.push(field.to_string()),
+            projection => *projection = LiveProjection::Fields(vec![field.to_string()]),
+        }
+        self
+    }
+
+    /// Sends only the value of `field` (`LIVE SELECT VALUE field`).
+    pub fn value(&mut self, field: &str) -> &mut Self {
+        self.projection = LiveProjection::Value(field.to_string());
+        self
+    }
+
+    /// Sends JSON Patch diffs instead of records (`LIVE SELECT DIFF`).
+    pub fn diff(&mut self) -> &mut Self {
+        self.projection = LiveProjection::Diff;
+        self
+    }
+
+    /// Only notify about records matching the condition. All top-level
+    /// conditions are joined by AND.
+    pub fn where_complex(&mut self, condition: impl Into<Condition>) -> &mut Self {
+        self.where_clauses.push(condition.into());
+        self
+    }
+
+    /// Adds a field to the FETCH clause. Can be called multiple times.
+    pub fn fetch(&mut self, field: &str) -> &mut Self {
+        self.fetch_clauses.push(field.to_string());
+        self
+    }
+
+    /// Build the statement.
+    pub fn build(&self) -> String {
+        let projection = match &self.projection {
+            LiveProjection::Fields(fields) if fields.is_empty() => "*".to_string(),
+            LiveProjection::Fields(fields) => fields.join(", "),
+            LiveProjection::Value(field) => format!("VALUE {}", field),
+            LiveProjection::Diff => "DIFF".to_string(),
+        };
+        let mut out = format!(
+            "LIVE SELECT {} FROM {}",
+            projection,
+            escape_ident(&self.table)
+        );
+        if !self.where_clauses.is_empty() {
+            let rendered: Vec<String> = dedup_conditions(&self.where_clauses)
+                .into_iter()
+                .map(render_condition)
+                .collect();
+            out.push_str(" WHERE ");
+            out.push_str(&rendered.join(" AND "));
+        }
+        if !self.fetch_clauses.is_empty() {
+            out.push_str(" FETCH ");
+            out.push_str(&self.fetch_clauses.join(", "));
+        }
+        out
+    }
+}
+
+/// Renders `KILL <id>`, stopping a live query. `id` is the UUID returned
+/// by `LIVE SELECT`, as a string (rendered as a `u'...'` literal), a `Uuid`
+/// with the `uuid` feature, or a parameter such as `param("live_id")`.
+/// Returns Err if a string is not a valid UUID.
+pub fn kill(id: impl Into<Value>) -> Result<String, BuildError> {
+    let id = match id.into() {
+        Value::String(s) if is_uuid(&s) => format!("u{}", quote_string(&s)),
+        Value::String(_) => {
+            return Err(BuildError::new("Invalid live query id.")
+                .with_suggestion("pass the UUID returned by LIVE SELECT"));
+        }
+        value => value.to_surql(),
+    };
+    Ok(format!("KILL {}", id))
+}
+
+/// True for the hyphenated `8-4-4-4-12` hex form.
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_select_modes() {
+        let sql = LiveQueryBuilder::new("order")
+            .where_complex(Condition::eq("status", "open"))
+            .where_complex(Condition::gt("total", 100))
+            .fetch("customer")
+            .build();
+        assert_eq!(
+            sql,
+            "LIVE SELECT * FROM `order` WHERE status = 'open' AND total > 100 FETCH customer"
+        );
+        assert_eq!(
+            LiveQueryBuilder::new("user").diff().build(),
+            "LIVE SELECT DIFF FROM user"
+        );
+        assert_eq!(
+            LiveQueryBuilder::new("user")
+                .select("id")
+                .select("name")
+                .build(),
+            "LIVE SELECT id, name FROM user"
+        );
+        assert_eq!(
+            LiveQueryBuilder::new("user").value("name").build(),
+            "LIVE SELECT VALUE name FROM user"
+        );
+    }
+
+    #[test]
+    fn kill_live_query() {
+        assert_eq!(
+            kill("b9b7d6bb-5e8a-4c3b-9b4e-2c9f4f5d1a2e").unwrap(),
+            "KILL u'b9b7d6bb-5e8a-4c3b-9b4e-2c9f4f5d1a2e'"
+        );
+        assert_eq!(kill(crate::param("live_id")).unwrap(), "KILL $live_id");
+        assert_eq!(
+            kill("1; REMOVE TABLE user").unwrap_err(),
+            "Invalid live query id."
+        );
+    }
+}
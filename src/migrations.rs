@@ -0,0 +1,535 @@
+//! Generators for schema and data migration scripts.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::define::render_field_name;
+use crate::{
+    BuildError, Condition, DefineAccessBuilder, DefineAnalyzerBuilder, DefineDatabaseBuilder,
+    DefineEventBuilder, DefineFieldBuilder, DefineFunctionBuilder, DefineIndexBuilder,
+    DefineNamespaceBuilder, DefineParamBuilder, DefineTableBuilder, ForLoopBuilder, QueryBuilder,
+    RemoveBuilder, SurrealSchema, TransactionBuilder, escape_ident, func, param,
+};
+
+/// Table recording applied migrations, one `_migration:<version>` record
+/// each.
+pub const MIGRATIONS_TABLE: &str = "_migration";
+
+/// Builds the statements that safely rename a field, wrapped in a
+/// transaction:
+///
+/// 1. `DEFINE FIELD <to>` (with the given type, if any) so SCHEMAFULL tables
+///    accept the new field,
+/// 2. `UPDATE <table> SET <to> = <from>` to copy the data,
+/// 3. `UPDATE <table> UNSET <from>` to drop the old values,
+/// 4. `REMOVE FIELD <from>` to drop the old definition.
+///
+/// Use an `option<...>` type if some records may lack the field.
+pub fn rename_field(
+    table: &str,
+    from: &str,
+    to: &str,
+    field_type: Option<&str>,
+) -> TransactionBuilder {
+    let table = escape_ident(table);
+    let from = render_field_name(from);
+    let to = render_field_name(to);
+    let define = match field_type {
+        Some(ty) => format!("DEFINE FIELD {} ON TABLE {} TYPE {}", to, table, ty),
+        None => format!("DEFINE FIELD {} ON TABLE {}", to, table),
+    };
+
+    let mut tb = TransactionBuilder::new();
+    tb.begin()
+        .add_statement(&define)
+        .add_statement(&format!("UPDATE {} SET {} = {}", table, to, from))
+        .add_statement(&format!("UPDATE {} UNSET {}", table, from))
+        .add_statement(&format!("REMOVE FIELD {} ON TABLE {}", from, table))
+        .commit();
+    tb
+}
+
+/// Generates a batched backfill script for large data migrations.
+///
+/// The ids to update are snapshotted once with a LET, then updated in chunks
+/// of `batch_size`, optionally sleeping after each batch to limit load:
+///
+/// ```text
+/// LET $ids = (SELECT VALUE id FROM user WHERE status IS NONE ORDER BY id);
+/// FOR $batch IN array::clump($ids, 500) { UPDATE $batch SET status = 'active'; SLEEP 100ms; };
+/// ```
+///
+/// Because the ids are captured up front, updates that stop rows matching the
+/// filter do not shift later batches.
+#[derive(Debug, Clone)]
+pub struct Backfill {
+    table: String,
+    assignments: Vec<String>,
+    filter: Option<Condition>,
+    batch_size: u64,
+    sleep: Option<String>,
+}
+
+impl Backfill {
+    /// Create a backfill over `table` with a default batch size of 1000.
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            assignments: Vec::new(),
+            filter: None,
+            batch_size: 1000,
+            sleep: None,
+        }
+    }
+
+    /// Add a SET assignment, e.g. `status = 'active'`. Can be called multiple times.
+    pub fn set(&mut self, assignment: &str) -> &mut Self {
+        self.assignments.push(assignment.to_string());
+        self
+    }
+
+    /// Only backfill records matching the condition.
+    pub fn filter(&mut self, condition: Condition) -> &mut Self {
+        self.filter = Some(condition);
+        self
+    }
+
+    /// Set the number of records updated per batch.
+    pub fn batch_size(&mut self, size: u64) -> &mut Self {
+        self.batch_size = size;
+        self
+    }
+
+    /// Sleep for the given SurrealQL duration (e.g. `100ms`) after each batch.
+    pub fn sleep(&mut self, duration: &str) -> &mut Self {
+        self.sleep = Some(duration.to_string());
+        self
+    }
+
+    /// Build the script. Returns Err if no assignment was added or the batch
+    /// size is zero.
+    pub fn build(&self) -> Result<String, BuildError> {
+        if self.assignments.is_empty() {
+            return Err(
+                BuildError::new("A backfill requires at least one SET assignment.")
+                    .with_suggestion("call .set(assignment) before .build()"),
+            );
+        }
+        if self.batch_size == 0 {
+            return Err(BuildError::new("The batch size must be greater than zero.")
+                .with_suggestion("pass a non-zero value to .batch_size()"));
+        }
+
+        let mut ids = QueryBuilder::new();
+        ids.select("VALUE id", None)
+            .from(self.table.as_str())
+            .order_by("id");
+        if let Some(filter) = &self.filter {
+            ids.where_complex(filter.clone());
+        }
+
+        let chunks = func("array::clump")
+            .arg(param("ids"))
+            .arg(self.batch_size as i64);
+        let mut lp = ForLoopBuilder::over("batch", chunks);
+        lp.statement(&format!(
+            "UPDATE $batch SET {}",
+            self.assignments.join(", ")
+        ));
+        if let Some(duration) = &self.sleep {
+            lp.statement(&format!("SLEEP {}", duration));
+        }
+        Ok(format!("LET $ids = ({});\n{}", ids.build()?, lp.build()))
+    }
+}
+
+/// A single schema definition tracked by a [`Schema`].
+#[derive(Debug, Clone)]
+pub enum Definition {
+    /// `DEFINE NAMESPACE`
+    Namespace(DefineNamespaceBuilder),
+    /// `DEFINE DATABASE`
+    Database(DefineDatabaseBuilder),
+    /// `DEFINE ANALYZER`
+    Analyzer(DefineAnalyzerBuilder),
+    /// `DEFINE PARAM`
+    Param(DefineParamBuilder),
+    /// `DEFINE FUNCTION`
+    Function(DefineFunctionBuilder),
+    /// `DEFINE ACCESS`
+    Access(DefineAccessBuilder),
+    /// `DEFINE TABLE`
+    Table(DefineTableBuilder),
+    /// `DEFINE FIELD`
+    Field(DefineFieldBuilder),
+    /// `DEFINE INDEX`
+    Index(DefineIndexBuilder),
+    /// `DEFINE EVENT`
+    Event(DefineEventBuilder),
+}
+
+macro_rules! definition_from {
+    ($($variant:ident($builder:ty)),* $(,)?) => {
+        $(
+            impl From<$builder> for Definition {
+                fn from(builder: $builder) -> Self {
+                    Definition::$variant(builder)
+                }
+            }
+
+            impl From<&mut $builder> for Definition {
+                fn from(builder: &mut $builder) -> Self {
+                    Definition::$variant(builder.clone())
+                }
+            }
+        )*
+    };
+}
+
+definition_from!(
+    Namespace(DefineNamespaceBuilder),
+    Database(DefineDatabaseBuilder),
+    Analyzer(DefineAnalyzerBuilder),
+    Param(DefineParamBuilder),
+    Function(DefineFunctionBuilder),
+    Access(DefineAccessBuilder),
+    Table(DefineTableBuilder),
+    Field(DefineFieldBuilder),
+    Index(DefineIndexBuilder),
+    Event(DefineEventBuilder),
+);
+
+impl Definition {
+    /// Build the DEFINE statement.
+    pub fn build(&self) -> Result<String, BuildError> {
+        Ok(match self {
+            Definition::Namespace(b) => b.build(),
+            Definition::Database(b) => b.build(),
+            Definition::Analyzer(b) => b.build(),
+            Definition::Param(b) => b.build(),
+            Definition::Function(b) => b.build()?,
+            Definition::Access(b) => b.build()?,
+            Definition::Table(b) => b.build(),
+            Definition::Field(b) => b.build(),
+            Definition::Index(b) => b.build()?,
+            Definition::Event(b) => b.build()?,
+        })
+    }
+
+    /// The REMOVE statement undoing this definition; it also identifies the
+    /// defined object when diffing.
+    pub fn removal(&self) -> RemoveBuilder {
+        match self {
+            Definition::Namespace(b) => b.removal(),
+            Definition::Database(b) => b.removal(),
+            Definition::Analyzer(b) => b.removal(),
+            Definition::Param(b) => b.removal(),
+            Definition::Function(b) => b.removal(),
+            Definition::Access(b) => b.removal(),
+            Definition::Table(b) => b.removal(),
+            Definition::Field(b) => b.removal(),
+            Definition::Index(b) => b.removal(),
+            Definition::Event(b) => b.removal(),
+        }
+    }
+
+    /// Definitions are applied in ascending rank, so dependencies such as
+    /// tables and analyzers exist before the fields and indexes using them.
+    fn rank(&self) -> u8 {
+        match self {
+            Definition::Namespace(_) => 0,
+            Definition::Database(_) => 1,
+            Definition::Analyzer(_) => 2,
+            Definition::Param(_) => 3,
+            Definition::Function(_) => 4,
+            Definition::Access(_) => 5,
+            Definition::Table(_) => 6,
+            Definition::Field(_) => 7,
+            Definition::Index(_) => 8,
+            Definition::Event(_) => 9,
+        }
+    }
+}
+
+/// A set of schema definitions, diffed against another set to generate a
+/// [`Migration`].
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    definitions: Vec<Definition>,
+}
+
+impl Schema {
+    /// Creates an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a definition, e.g. a `DefineTableBuilder`.
+    pub fn define(&mut self, definition: impl Into<Definition>) -> &mut Self {
+        self.definitions.push(definition.into());
+        self
+    }
+
+    /// Adds the table, field and index definitions of a [`SurrealSchema`]
+    /// type.
+    pub fn derived<T: SurrealSchema>(&mut self) -> &mut Self {
+        self.define(T::define_table());
+        for field in T::define_fields() {
+            self.define(field);
+        }
+        for index in T::define_indexes() {
+            self.define(index);
+        }
+        self
+    }
+
+    /// The definitions, in the order they were added.
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    /// Diffs this schema against `target`: objects only in `target` are
+    /// defined, changed ones are redefined with `OVERWRITE`, and objects
+    /// missing from `target` are removed. The down script reverses the
+    /// change. Returns Err if a definition cannot be built.
+    pub fn diff(&self, target: &Schema) -> Result<Migration, BuildError> {
+        Ok(Migration {
+            up: migration_steps(self, target)?,
+            down: migration_steps(target, self)?,
+        })
+    }
+
+    /// Maps each object's REMOVE statement to its rank and DEFINE statement.
+    fn index(&self) -> Result<BTreeMap<String, (u8, String)>, BuildError> {
+        self.definitions
+            .iter()
+            .map(|d| Ok((d.removal().build(), (d.rank(), d.build()?))))
+            .collect()
+    }
+}
+
+/// The statements turning the `from` schema into the `to` schema: removals
+/// first, dependents before what they depend on, then definitions.
+fn migration_steps(from: &Schema, to: &Schema) -> Result<Vec<String>, BuildError> {
+    let old = from.index()?;
+    let new = to.index()?;
+
+    let mut removes: Vec<(u8, String)> = old
+        .iter()
+        .filter(|(key, _)| !new.contains_key(*key))
+        .map(|(key, (rank, _))| (*rank, key.clone()))
+        .collect();
+    removes.sort_by_key(|(rank, _)| Reverse(*rank));
+
+    let mut defines: Vec<(u8, String)> = Vec::new();
+    let mut seen = BTreeSet::new();
+    for definition in &to.definitions {
+        let key = definition.removal().build();
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let (rank, stmt) = &new[&key];
+        match old.get(&key) {
+            None => defines.push((*rank, stmt.clone())),
+            Some((_, previous)) if previous != stmt => defines.push((*rank, overwrite(stmt))),
+            Some(_) => {}
+        }
+    }
+    defines.sort_by_key(|(rank, _)| *rank);
+
+    Ok(removes
+        .into_iter()
+        .chain(defines)
+        .map(|(_, stmt)| stmt)
+        .collect())
+}
+
+/// `DEFINE FIELD x ...` -> `DEFINE FIELD OVERWRITE x ...`.
+fn overwrite(stmt: &str) -> String {
+    let mut parts = stmt.splitn(3, ' ');
+    let (define, kind, rest) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let rest = rest.strip_prefix("IF NOT EXISTS ").unwrap_or(rest);
+    format!("{} {} OVERWRITE {}", define, kind, rest)
+}
+
+/// The result of [`Schema::diff`]: forward and backward statements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// Statements applying the migration.
+    pub up: Vec<String>,
+    /// Statements reverting it.
+    pub down: Vec<String>,
+}
+
+impl Migration {
+    /// True if the schemas were identical.
+    pub fn is_empty(&self) -> bool {
+        self.up.is_empty() && self.down.is_empty()
+    }
+
+    /// The forward statements, one per line.
+    pub fn up_script(&self) -> String {
+        self.up.join("\n")
+    }
+
+    /// The backward statements, one per line.
+    pub fn down_script(&self) -> String {
+        self.down.join("\n")
+    }
+
+    /// The forward statements as a transaction that first records `version`
+    /// in [`MIGRATIONS_TABLE`]. Creating the record fails if the version was
+    /// already applied, cancelling the whole transaction.
+    pub fn up_transaction(&self, version: u64) -> TransactionBuilder {
+        let mut tb = TransactionBuilder::new();
+        tb.begin().add_statement(&format!(
+            "CREATE {}:{} SET applied_at = time::now()",
+            MIGRATIONS_TABLE, version
+        ));
+        for stmt in &self.up {
+            tb.add_statement(stmt);
+        }
+        tb.commit();
+        tb
+    }
+
+    /// The backward statements as a transaction that deletes the `version`
+    /// record, so the migration can be applied again.
+    pub fn down_transaction(&self, version: u64) -> TransactionBuilder {
+        let mut tb = TransactionBuilder::new();
+        tb.begin();
+        for stmt in &self.down {
+            tb.add_statement(stmt);
+        }
+        tb.add_statement(&format!("DELETE {}:{}", MIGRATIONS_TABLE, version))
+            .commit();
+        tb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_field_script() {
+        let script = rename_field("user", "mail", "email", Some("string")).build();
+        assert_eq!(
+            script,
+            "BEGIN TRANSACTION;\n\
+             DEFINE FIELD email ON TABLE user TYPE string;\n\
+             UPDATE user SET email = mail;\n\
+             UPDATE user UNSET mail;\n\
+             REMOVE FIELD mail ON TABLE user;\n\
+             COMMIT TRANSACTION;"
+        );
+    }
+
+    #[test]
+    fn backfill_in_batches_with_sleep() {
+        let script = Backfill::new("user")
+            .set("status = 'active'")
+            .filter(Condition::Simple("status IS NONE".into()))
+            .batch_size(500)
+            .sleep("100ms")
+            .build()
+            .unwrap();
+        assert_eq!(
+            script,
+            "LET $ids = (SELECT VALUE id FROM user WHERE status IS NONE ORDER BY id);\n\
+             FOR $batch IN array::clump($ids, 500) \
+             { UPDATE $batch SET status = 'active'; SLEEP 100ms; };"
+        );
+    }
+
+    #[test]
+    fn backfill_requires_assignments() {
+        assert_eq!(
+            Backfill::new("user").build().unwrap_err(),
+            "A backfill requires at least one SET assignment."
+        );
+        assert_eq!(
+            Backfill::new("user")
+                .set("a = 1")
+                .batch_size(0)
+                .build()
+                .unwrap_err(),
+            "The batch size must be greater than zero."
+        );
+    }
+
+    #[test]
+    fn schema_diff_up_and_down() {
+        let mut v1 = Schema::new();
+        v1.define(DefineTableBuilder::new("user").schemafull())
+            .define(DefineFieldBuilder::new("name", "user").r#type("string"))
+            .define(DefineFieldBuilder::new("nick", "user").r#type("string"));
+
+        let mut v2 = Schema::new();
+        v2.define(DefineTableBuilder::new("user").schemafull())
+            .define(
+                DefineIndexBuilder::new("user_email", "user")
+                    .field("email")
+                    .unique(),
+            )
+            .define(DefineFieldBuilder::new("email", "user").r#type("string"))
+            .define(DefineFieldBuilder::new("name", "user").r#type("option<string>"));
+
+        let migration = v1.diff(&v2).unwrap();
+        assert_eq!(
+            migration.up,
+            vec![
+                "REMOVE FIELD nick ON TABLE user;",
+                "DEFINE FIELD email ON TABLE user TYPE string;",
+                "DEFINE FIELD OVERWRITE name ON TABLE user TYPE option<string>;",
+                "DEFINE INDEX user_email ON TABLE user FIELDS email UNIQUE;",
+            ]
+        );
+        assert_eq!(
+            migration.down,
+            vec![
+                "REMOVE INDEX user_email ON TABLE user;",
+                "REMOVE FIELD email ON TABLE user;",
+                "DEFINE FIELD OVERWRITE name ON TABLE user TYPE string;",
+                "DEFINE FIELD nick ON TABLE user TYPE string;",
+            ]
+        );
+        assert!(v2.diff(&v2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn versioned_migration_transaction() {
+        let mut target = Schema::new();
+        target.define(DefineTableBuilder::new("post"));
+        let migration = Schema::new().diff(&target).unwrap();
+        assert_eq!(
+            migration.up_transaction(3).build(),
+            "BEGIN TRANSACTION;\n\
+             CREATE _migration:3 SET applied_at = time::now();\n\
+             DEFINE TABLE post;\n\
+             COMMIT TRANSACTION;"
+        );
+        assert_eq!(
+            migration.down_transaction(3).build(),
+            "BEGIN TRANSACTION;\n\
+             REMOVE TABLE post;\n\
+             DELETE _migration:3;\n\
+             COMMIT TRANSACTION;"
+        );
+    }
+
+    #[test]
+    fn rename_field_escapes_identifiers() {
+        let script = rename_field("user-profile", "full name", "name", None).build();
+        assert!(script.contains("DEFINE FIELD name ON TABLE `user-profile`;"));
+        assert!(script.contains("UPDATE `user-profile` SET name = `full name`;"));
+
+        let script = rename_field("user", "profile.mail", "profile.email", None).build();
+        assert!(script.contains("UPDATE user SET profile.email = profile.mail;"));
+        assert!(script.contains("REMOVE FIELD profile.mail ON TABLE user;"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Threshold-based reporting of slow or oversized query builds.
+
+use std::time::{Duration, Instant};
+
+use crate::{BuildError, QueryBuilder, StatementKind};
+
+/// Statistics about a build that crossed a [`BuildMonitor`] threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildReport {
+    /// The kind of statement built.
+    pub kind: StatementKind,
+    /// Time spent rendering.
+    pub elapsed: Duration,
+    /// Length of the rendered query in bytes.
+    pub len: usize,
+    /// Hash of the query with literals masked, so builds of the same shape
+    /// share a fingerprint. Stable across runs and platforms.
+    pub fingerprint: u64,
+    /// Number of WHERE conditions.
+    pub where_clauses: usize,
+    /// Number of FETCH clauses.
+    pub fetches: usize,
+    /// Number of projected fields and subqueries.
+    pub selects: usize,
+    /// The `complexity()` score.
+    pub complexity: u32,
+}
+
+/// Calls a hook for builds exceeding a duration or output size.
+pub struct BuildMonitor {
+    max_duration: Option<Duration>,
+    max_len: Option<usize>,
+    hook: Box<dyn Fn(&BuildReport) + Send + Sync>,
+}
+
+impl std::fmt::Debug for BuildMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuildMonitor")
+            .field("max_duration", &self.max_duration)
+            .field("max_len", &self.max_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BuildMonitor {
+    /// Creates a monitor with no thresholds; set at least one to get reports.
+    pub fn new(hook: impl Fn(&BuildReport) + Send + Sync + 'static) -> Self {
+        Self {
+            max_duration: None,
+            max_len: None,
+            hook: Box::new(hook),
+        }
+    }
+
+    /// Report builds that take longer than `max`.
+    pub fn max_duration(&mut self, max: Duration) -> &mut Self {
+        self.max_duration = Some(max);
+        self
+    }
+
+    /// Report builds whose output is longer than `max` bytes.
+    pub fn max_len(&mut self, max: usize) -> &mut Self {
+        self.max_len = Some(max);
+        self
+    }
+
+    fn exceeded(&self, elapsed: Duration, len: usize) -> bool {
+        self.max_duration.is_some_and(|max| elapsed > max)
+            || self.max_len.is_some_and(|max| len > max)
+    }
+}
+
+impl QueryBuilder {
+    /// Builds the query like `build()`, calling the monitor's hook when the
+    /// build crosses one of its thresholds.
+    pub fn build_monitored(&self, monitor: &BuildMonitor) -> Result<String, BuildError> {
+        let started = Instant::now();
+        let sql = self.build()?;
+        let elapsed = started.elapsed();
+        if monitor.exceeded(elapsed, sql.len()) {
+            (monitor.hook)(&BuildReport {
+                kind: self.kind(),
+                elapsed,
+                len: sql.len(),
+                fingerprint: fingerprint(&sql),
+                where_clauses: self.where_clauses.len(),
+                fetches: self.fetch_clauses.len(),
+                selects: self.select_items.len(),
+                complexity: self.complexity(),
+            });
+        }
+        Ok(sql)
+    }
+}
+
+/// FNV-1a over the query with string literals and numbers replaced by `?`.
+fn fingerprint(sql: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |b: u8| {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    };
+    let mut chars = sql.chars().peekable();
+    let mut prev_ident = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some(n) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == c {
+                        break;
+                    }
+                }
+                feed(b'?');
+            }
+            '0'..='9' if !prev_ident => {
+                while chars
+                    .peek()
+                    .is_some_and(|n| n.is_ascii_digit() || *n == '.')
+                {
+                    chars.next();
+                }
+                feed(b'?');
+            }
+            _ => {
+                let mut buf = [0; 4];
+                c.encode_utf8(&mut buf).bytes().for_each(&mut feed);
+            }
+        }
+        prev_ident = c.is_alphanumeric() || c == '_';
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Condition;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn reports_builds_over_threshold() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let mut monitor = BuildMonitor::new(move |r| sink.lock().unwrap().push(r.clone()));
+        monitor.max_len(40);
+
+        let short = QueryBuilder::new().from("user").build_monitored(&monitor);
+        assert_eq!(short.unwrap(), "SELECT * FROM user");
+        assert!(reports.lock().unwrap().is_empty());
+
+        let long = |age: i64| {
+            QueryBuilder::new()
+                .from("user")
+                .where_complex(Condition::gt("age", age))
+                .where_complex(Condition::eq("name", "somebody"))
+                .build_monitored(&monitor)
+                .unwrap()
+        };
+        long(18);
+        long(21);
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].kind, StatementKind::Select);
+        assert_eq!(reports[0].where_clauses, 2);
+        assert_eq!(reports[0].fingerprint, reports[1].fingerprint);
+        assert_ne!(reports[0].fingerprint, fingerprint("SELECT * FROM post"));
+    }
+}
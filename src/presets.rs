@@ -0,0 +1,348 @@
+//! Generators for common query patterns that are fiddly to compose by hand.
+
+use crate::{
+    BuildError, Condition, Path, PermissionAction, QueryBuilder, RecordId, Value, escape_ident,
+    parent_field, quote_string, render_condition,
+};
+
+/// A single probe: the action it tests and the script to run as the record
+/// user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionProbe {
+    /// The action being probed.
+    pub action: PermissionAction,
+    /// The script to execute.
+    pub script: String,
+}
+
+/// Generates the SELECT/CREATE/UPDATE/DELETE probe queries needed to test a
+/// table's PERMISSIONS clauses for a record user.
+///
+/// Every probe starts with a guard that throws unless the session is signed
+/// in through the given record access method as the expected `$auth` record,
+/// so a misconfigured test session cannot silently pass. The write probes
+/// modify data and are meant for disposable test databases.
+#[derive(Debug, Clone)]
+pub struct PermissionProbes {
+    table: String,
+    access: String,
+    auth: RecordId,
+    record: Option<RecordId>,
+    content: Option<String>,
+}
+
+impl PermissionProbes {
+    /// Create probes for `table`, run as `auth` signed in via `access`.
+    pub fn new(table: &str, access: &str, auth: impl Into<RecordId>) -> Self {
+        Self {
+            table: table.to_string(),
+            access: access.to_string(),
+            auth: auth.into(),
+            record: None,
+            content: None,
+        }
+    }
+
+    /// Target a specific record instead of the whole table.
+    pub fn record(&mut self, id: impl Into<RecordId>) -> &mut Self {
+        self.record = Some(id.into());
+        self
+    }
+
+    /// Object expression used as CONTENT for the CREATE probe,
+    /// e.g. `{ title: 'probe' }`.
+    pub fn content(&mut self, object: &str) -> &mut Self {
+        self.content = Some(object.to_string());
+        self
+    }
+
+    /// Build the four probes, in SELECT, CREATE, UPDATE, DELETE order.
+    pub fn build(&self) -> Vec<PermissionProbe> {
+        let target = match &self.record {
+            Some(id) => id.to_surql(),
+            None => escape_ident(&self.table),
+        };
+        let guard = format!(
+            "IF $access != {} OR $auth.id != {} {{ THROW {}; }};",
+            quote_string(&self.access),
+            self.auth.to_surql(),
+            quote_string(&format!(
+                "permission probe must run as {} via access {}",
+                self.auth, self.access
+            ))
+        );
+        let create = match &self.content {
+            Some(content) => format!("CREATE {} CONTENT {} RETURN AFTER;", target, content),
+            None => format!("CREATE {} RETURN AFTER;", target),
+        };
+        [
+            (
+                PermissionAction::Select,
+                format!("SELECT * FROM {};", target),
+            ),
+            (PermissionAction::Create, create),
+            (
+                PermissionAction::Update,
+                format!("UPDATE {} RETURN AFTER;", target),
+            ),
+            (
+                PermissionAction::Delete,
+                format!("DELETE {} RETURN BEFORE;", target),
+            ),
+        ]
+        .into_iter()
+        .map(|(action, stmt)| PermissionProbe {
+            action,
+            script: format!("{}\n{}", guard, stmt),
+        })
+        .collect()
+    }
+}
+
+/// How a field is scrubbed by [`Anonymize`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scrub {
+    /// Replace the value with its SHA-256 hash: `crypto::sha256(<string> field)`.
+    Hash,
+    /// Replace the value with a fixed literal.
+    Fixed(Value),
+    /// Replace the value with a random alphanumeric string of the given length.
+    RandomString(usize),
+    /// Remove the field (`field = NONE`).
+    Remove,
+}
+
+/// Generates an UPDATE that scrubs PII from a table, for seeding
+/// non-production environments from production data.
+#[derive(Debug, Clone)]
+pub struct Anonymize {
+    table: String,
+    fields: Vec<(String, Scrub)>,
+    filter: Option<Condition>,
+}
+
+impl Anonymize {
+    /// Create an anonymization script for `table`.
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            fields: Vec::new(),
+            filter: None,
+        }
+    }
+
+    /// Scrub `field` with the given strategy. Fields render in the order
+    /// they were added.
+    pub fn field(&mut self, field: &str, strategy: Scrub) -> &mut Self {
+        self.fields.push((field.to_string(), strategy));
+        self
+    }
+
+    /// Add several field strategies at once, e.g. from a map.
+    pub fn fields<K: AsRef<str>>(
+        &mut self,
+        fields: impl IntoIterator<Item = (K, Scrub)>,
+    ) -> &mut Self {
+        for (field, strategy) in fields {
+            self.field(field.as_ref(), strategy);
+        }
+        self
+    }
+
+    /// Only scrub records matching the condition.
+    pub fn filter(&mut self, condition: Condition) -> &mut Self {
+        self.filter = Some(condition);
+        self
+    }
+
+    /// Build the UPDATE statement. Returns Err if no field was added.
+    pub fn build(&self) -> Result<String, BuildError> {
+        if self.fields.is_empty() {
+            return Err(BuildError::new("At least one field must be anonymized.")
+                .with_suggestion("call .field(name, scrub) before .build()"));
+        }
+        let assignments: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(field, strategy)| {
+                let field = escape_ident(field);
+                let value = match strategy {
+                    Scrub::Hash => format!("crypto::sha256(<string> {})", field),
+                    Scrub::Fixed(v) => v.to_surql(),
+                    Scrub::RandomString(len) => format!("rand::string({})", len),
+                    Scrub::Remove => "NONE".to_string(),
+                };
+                format!("{} = {}", field, value)
+            })
+            .collect();
+        let mut out = format!(
+            "UPDATE {} SET {}",
+            escape_ident(&self.table),
+            assignments.join(", ")
+        );
+        if let Some(filter) = &self.filter {
+            out.push_str(" WHERE ");
+            out.push_str(&render_condition(filter));
+        }
+        out.push(';');
+        Ok(out)
+    }
+}
+
+/// Builds a query finding values of `fields` that occur more than once in
+/// `table`, with their number of occurrences in a `count` field:
+///
+/// ```text
+/// SELECT * FROM (SELECT email, count() AS count FROM user GROUP BY email) WHERE count > 1
+/// ```
+///
+/// The returned builder can be further refined, e.g. with `order_by("count DESC")`.
+pub fn duplicates(table: &str, fields: &[&str]) -> QueryBuilder {
+    let mut grouped = QueryBuilder::new();
+    grouped
+        .select_fields(fields)
+        .select_count(Some("count"))
+        .from(table);
+    for field in fields {
+        grouped.group_by(field);
+    }
+
+    let mut qb = QueryBuilder::new();
+    qb.from(grouped).where_complex(Condition::gt("count", 1));
+    qb
+}
+
+/// Builds a "top N records per group" query: one row per distinct value of
+/// `group_field`, with the first `n` records of that group (sorted by
+/// `order`, e.g. `"score DESC"`) in a `top` field:
+///
+/// ```text
+/// SELECT team, (SELECT * FROM player WHERE team = $parent.team ORDER BY score DESC LIMIT 3) AS top
+///     FROM (SELECT team FROM player GROUP BY team)
+/// ```
+pub fn top_n_per_group(
+    table: &str,
+    group_field: &str,
+    order: &str,
+    n: u64,
+) -> Result<QueryBuilder, BuildError> {
+    let field = Path::from(group_field);
+    let mut groups = QueryBuilder::new();
+    groups
+        .select_path(&field, None)
+        .from(table)
+        .group_by(&field.to_surql());
+
+    let mut top = QueryBuilder::new();
+    top.from(table)
+        .where_complex(Condition::eq(group_field, parent_field(group_field)))
+        .order_by(order)
+        .limit(n);
+
+    let mut qb = QueryBuilder::new();
+    qb.select_path(&field, None)
+        .select_subquery(&top, Some("top"))?
+        .from(groups);
+    Ok(qb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_probes_for_record_user() {
+        let mut probes = PermissionProbes::new("post", "account", RecordId::new("user", "alice"));
+        probes
+            .record(RecordId::new("post", "probe"))
+            .content("{ title: 'probe' }");
+        let probes = probes.build();
+
+        assert_eq!(probes.len(), 4);
+        let guard = "IF $access != 'account' OR $auth.id != user:alice { THROW 'permission probe must run as user:alice via access account'; };";
+        assert_eq!(
+            probes[0].script,
+            format!("{}\nSELECT * FROM post:probe;", guard)
+        );
+        assert_eq!(probes[1].action, PermissionAction::Create);
+        assert!(
+            probes[1]
+                .script
+                .ends_with("CREATE post:probe CONTENT { title: 'probe' } RETURN AFTER;")
+        );
+        assert!(
+            probes[3]
+                .script
+                .ends_with("DELETE post:probe RETURN BEFORE;")
+        );
+    }
+
+    #[test]
+    fn anonymize_fields() {
+        let sql = Anonymize::new("user")
+            .field("email", Scrub::Hash)
+            .field("name", Scrub::Fixed("redacted".into()))
+            .field("api-key", Scrub::RandomString(32))
+            .field("phone", Scrub::Remove)
+            .filter(Condition::ne("role", "admin"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE user SET email = crypto::sha256(<string> email), name = 'redacted', \
+             `api-key` = rand::string(32), phone = NONE WHERE role != 'admin';"
+        );
+        assert!(Anonymize::new("user").build().is_err());
+    }
+
+    #[test]
+    fn duplicates_by_fields() {
+        let sql = duplicates("user", &["first_name", "last_name"])
+            .order_by("count DESC")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT first_name, last_name, count() AS count FROM user \
+             GROUP BY first_name, last_name) WHERE count > 1 ORDER BY count DESC"
+        );
+    }
+
+    #[test]
+    fn top_n_per_group_query() {
+        let sql = top_n_per_group("player", "team", "score DESC", 3)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT team, (SELECT * FROM player WHERE team = $parent.team ORDER BY score DESC LIMIT 3) \
+             AS top FROM (SELECT team FROM player GROUP BY team)"
+        );
+        let sql = top_n_per_group("player", "home-team", "score DESC", 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT `home-team`, (SELECT * FROM player WHERE `home-team` = $parent.`home-team` \
+             ORDER BY score DESC LIMIT 1) AS top \
+             FROM (SELECT `home-team` FROM player GROUP BY `home-team`)"
+        );
+    }
+
+    #[test]
+    fn permission_probes_for_table() {
+        let probes = PermissionProbes::new("post", "account", ("user", 1)).build();
+        assert!(probes[1].script.ends_with("\nCREATE post RETURN AFTER;"));
+        assert!(probes[2].script.ends_with("\nUPDATE post RETURN AFTER;"));
+
+        let probes = PermissionProbes::new("user-pref", "account", ("user", 1)).build();
+        assert!(probes[0].script.ends_with("\nSELECT * FROM `user-pref`;"));
+        assert!(
+            probes[3]
+                .script
+                .ends_with("\nDELETE `user-pref` RETURN BEFORE;")
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! `#[derive(SurrealSchema)]` for surrealex. Use it through the `derive`
+//! feature of the `surrealex` crate rather than depending on this crate
+//! directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type, parse_macro_input,
+};
+
+/// Implements `surrealex::SurrealSchema`, mapping a struct to a
+/// `DEFINE TABLE` statement plus one `DEFINE FIELD` per field.
+///
+/// Struct attributes, all optional:
+/// - `#[surreal(table = "name")]`: the table, defaulting to the snake_case
+///   struct name.
+/// - `#[surreal(schemafull)]` or `#[surreal(schemaless)]`.
+///
+/// Field attributes, all optional:
+/// - `rename = "name"`: the field name in the database.
+/// - `type = "..."`: the field type, overriding the one inferred from the Rust
+///   type. Required for types that cannot be inferred.
+/// - `assert = "..."`, `default = "..."`, `value = "..."`: SurrealQL
+///   expressions for the matching clauses.
+/// - `readonly`, `flexible`.
+/// - `index` or `unique`: also define an index named `<table>_<field>`.
+/// - `skip`: leave the field out of the schema.
+#[proc_macro_derive(SurrealSchema, attributes(surreal))]
+pub fn derive_surreal_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct TableAttrs {
+    table: Option<String>,
+    schemafull: Option<bool>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    field_type: Option<String>,
+    assert: Option<String>,
+    default: Option<String>,
+    value: Option<String>,
+    readonly: bool,
+    flexible: bool,
+    index: bool,
+    unique: bool,
+    skip: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "SurrealSchema can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SurrealSchema can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut table_attrs = TableAttrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("surreal")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table_attrs.table = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("schemafull") {
+                table_attrs.schemafull = Some(true);
+            } else if meta.path.is_ident("schemaless") {
+                table_attrs.schemafull = Some(false);
+            } else {
+                return Err(meta.error("unknown surreal attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    let table = table_attrs
+        .table
+        .unwrap_or_else(|| snake_case(&input.ident.to_string()));
+
+    let mut field_defs = Vec::new();
+    let mut index_defs = Vec::new();
+    for field in fields {
+        let mut attrs = FieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("surreal")) {
+            attr.parse_nested_meta(|meta| {
+                let string = |meta: &syn::meta::ParseNestedMeta| -> syn::Result<String> {
+                    Ok(meta.value()?.parse::<LitStr>()?.value())
+                };
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(string(&meta)?);
+                } else if meta.path.is_ident("type") {
+                    attrs.field_type = Some(string(&meta)?);
+                } else if meta.path.is_ident("assert") {
+                    attrs.assert = Some(string(&meta)?);
+                } else if meta.path.is_ident("default") {
+                    attrs.default = Some(string(&meta)?);
+                } else if meta.path.is_ident("value") {
+                    attrs.value = Some(string(&meta)?);
+                } else if meta.path.is_ident("readonly") {
+                    attrs.readonly = true;
+                } else if meta.path.is_ident("flexible") {
+                    attrs.flexible = true;
+                } else if meta.path.is_ident("index") {
+                    attrs.index = true;
+                } else if meta.path.is_ident("unique") {
+                    attrs.unique = true;
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else {
+                    return Err(meta.error("unknown surreal attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        if attrs.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().expect("named field");
+        let name = attrs
+            .rename
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let field_type = match attrs.field_type {
+            Some(field_type) => field_type,
+            None => infer_type(&field.ty).ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &field.ty,
+                    format!(
+                        "cannot infer a SurrealQL type for `{}`; add #[surreal(type = \"...\")]",
+                        name
+                    ),
+                )
+            })?,
+        };
+
+        let mut clauses = vec![quote! { field.r#type(#field_type); }];
+        if attrs.flexible {
+            clauses.push(quote! { field.flexible(); });
+        }
+        if let Some(default) = attrs.default {
+            clauses.push(quote! { field.default(::surrealex::Raw::new(#default)); });
+        }
+        if attrs.readonly {
+            clauses.push(quote! { field.readonly(); });
+        }
+        if let Some(value) = attrs.value {
+            clauses.push(quote! { field.value(::surrealex::Raw::new(#value)); });
+        }
+        if let Some(assert) = attrs.assert {
+            clauses.push(quote! { field.assert(::surrealex::Raw::new(#assert)); });
+        }
+        field_defs.push(quote! {{
+            let mut field = ::surrealex::DefineFieldBuilder::new(#name, #table);
+            #(#clauses)*
+            field
+        }});
+
+        if attrs.index || attrs.unique {
+            let index_name = format!("{}_{}", table, name.replace('.', "_"));
+            let unique = attrs.unique.then(|| quote! { index.unique(); });
+            index_defs.push(quote! {{
+                let mut index = ::surrealex::DefineIndexBuilder::new(#index_name, #table);
+                index.field(#name);
+                #unique
+                index
+            }});
+        }
+    }
+
+    let schema = match table_attrs.schemafull {
+        Some(true) => quote! { table.schemafull(); },
+        Some(false) => quote! { table.schemaless(); },
+        None => quote! {},
+    };
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::surrealex::SurrealSchema for #ident #ty_generics #where_clause {
+            fn define_table() -> ::surrealex::DefineTableBuilder {
+                let mut table = ::surrealex::DefineTableBuilder::new(#table);
+                #schema
+                table
+            }
+
+            fn define_fields() -> ::std::vec::Vec<::surrealex::DefineFieldBuilder> {
+                ::std::vec![#(#field_defs),*]
+            }
+
+            fn define_indexes() -> ::std::vec::Vec<::surrealex::DefineIndexBuilder> {
+                ::std::vec![#(#index_defs),*]
+            }
+        }
+    })
+}
+
+/// `UserProfile` -> `user_profile`, `HTTPLog` -> `http_log`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let boundary = i > 0
+                && (chars[i - 1].is_lowercase()
+                    || chars.get(i + 1).is_some_and(|n| n.is_lowercase()));
+            if boundary {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Maps common Rust types to SurrealQL field types.
+fn infer_type(ty: &Type) -> Option<String> {
+    let ty = match ty {
+        Type::Reference(r) => &*r.elem,
+        ty => ty,
+    };
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let inner = || match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => infer_type(ty),
+            _ => None,
+        }),
+        _ => None,
+    };
+    let name = segment.ident.to_string();
+    let surql = match name.as_str() {
+        "String" | "str" | "char" => "string".to_string(),
+        "bool" => "bool".to_string(),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" => "int".to_string(),
+        "f32" | "f64" => "float".to_string(),
+        "Decimal" => "decimal".to_string(),
+        "DateTime" | "OffsetDateTime" => "datetime".to_string(),
+        "Duration" => "duration".to_string(),
+        "Uuid" => "uuid".to_string(),
+        "RecordId" => "record".to_string(),
+        "Point" => "geometry<point>".to_string(),
+        "Geometry" => "geometry".to_string(),
+        "HashMap" | "BTreeMap" => "object".to_string(),
+        "Option" => format!("option<{}>", inner()?),
+        "Vec" => format!("array<{}>", inner()?),
+        "HashSet" | "BTreeSet" => format!("set<{}>", inner()?),
+        "Box" => inner()?,
+        _ => return None,
+    };
+    Some(surql)
+}